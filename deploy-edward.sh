@@ -3,6 +3,14 @@ set -euo pipefail
 
 if [ -n "${1:-}" ]; then
   REMOTE_SERVER="$1"
+  CONFIG="$(dirname "$0")/edward.toml"
+
+  # the config goes first; the database is kept next to it in the remote home
+  ssh "$REMOTE_SERVER" '
+  set -euo pipefail
+  cat > ~/edward.toml.1
+  sed -i "s|^database = .*|database = \"$HOME/edward.db\"|" ~/edward.toml.1
+  ' < "$CONFIG"
 
   mkdir -p ~/pub
   cp ~/.target/release/rhbot ~/pub/dev
@@ -17,7 +25,8 @@ if [ -n "${1:-}" ]; then
   tmux send-keys C-c
   sleep 0.5
   mv ~/dev.1 ~/dev
-  tmux send-keys "./dev" Enter
+  mv ~/edward.toml.1 ~/edward.toml
+  tmux send-keys "./dev ~/edward.toml" Enter
   printf "NEW DEPLOYMENT HASH      :\t"
   sha256sum ~/dev
   echo "Successfully deployed"
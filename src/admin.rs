@@ -0,0 +1,125 @@
+use std::fmt::Write as _;
+
+use poise::serenity_prelude as serenity;
+use serenity::all::CreateEmbed;
+use poise::CreateReply;
+
+use crate::{fetch::Context, metrics::Timed};
+
+/// Inspect Edward's systems and switch them on and off for this server.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD", subcommands("list", "enable", "disable"))]
+pub async fn systems(_: Context<'_>) -> Result<(), anyhow::Error> { Ok(()) }
+
+/// Show which systems run for each event type, tier by tier.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn list(ctx: Context<'_>) -> Result<(), anyhow::Error>
+{
+    let disabled = ctx.data().toggles.disabled(ctx.guild_id());
+    let mut embed = CreateEmbed::new()
+        .title("Systems")
+        .description("Tiers run top to bottom; ~~struck out~~ systems are disabled here.")
+        .color(ctx.data().config.colors.header);
+
+    for (event, tiers) in ctx.data().registry.events() {
+        let mut field = String::new();
+
+        for tier in tiers.iter().filter(|tier| !tier.systems.is_empty()) {
+            writeln!(field, "**{}** ({})", tier.tier, tier.execution)?;
+
+            for system in &tier.systems {
+                let strike = if disabled.contains(system.name) { "~~" } else { "" };
+                writeln!(field, "{strike}`{}`{strike} - {}", system.name, system.description)?;
+            }
+        }
+
+        embed = embed.field(event, field, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// How long each system and tier takes, over its recent runs.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn stats(ctx: Context<'_>) -> Result<(), anyhow::Error>
+{
+    let mut embed = CreateEmbed::new()
+        .title("Stats")
+        .description("p50 / p95 / max over recent runs, total runs, and runs over the slow-system budget.")
+        .color(ctx.data().config.colors.header);
+
+    let mut events: Vec<(&'static str, String)> = vec![];
+    for ((event, timed), series) in ctx.data().metrics.series().iter() {
+        if events.last().is_none_or(|(last, _)| last != event) { events.push((event, String::new())); }
+        let Some((_, field)) = events.last_mut() else { continue };
+
+        let name = match timed {
+            Timed::Tier(tier) => format!("**{tier}**"),
+            Timed::System(system) => format!("`{system}`"),
+        };
+
+        writeln!(
+            field, "{name}: {:.1?} / {:.1?} / {:.1?}, {} runs, {} slow",
+            series.quantile(0.5), series.quantile(0.95), series.max(), series.count, series.slow
+        )?;
+    }
+
+    if events.is_empty() { embed = embed.description("Nothing has run yet."); }
+    for (event, field) in events {
+        embed = embed.field(event, field, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Turn a system back on.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn enable(
+    ctx: Context<'_>,
+    #[description = "System to enable"]
+    #[autocomplete = "autocomplete_system"]
+    system: String,
+) -> Result<(), anyhow::Error> {
+    toggle(ctx, &system, true).await
+}
+
+/// Stop a system from running on this server's events.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn disable(
+    ctx: Context<'_>,
+    #[description = "System to disable"]
+    #[autocomplete = "autocomplete_system"]
+    system: String,
+) -> Result<(), anyhow::Error> {
+    toggle(ctx, &system, false).await
+}
+
+async fn toggle(ctx: Context<'_>, system: &str, enabled: bool) -> Result<(), anyhow::Error>
+{
+    let Some(guild_id) = ctx.guild_id() else { return Ok(()) };
+    let state = if enabled { "enabled" } else { "disabled" };
+
+    if !ctx.data().registry.system_names().contains(&system) {
+        ctx.say(format!("There is no system called `{system}`.")).await?;
+        return Ok(());
+    }
+
+    let reply = if ctx.data().toggles.set_enabled(guild_id, system, enabled)? {
+        format!("`{system}` is now {state}.")
+    } else {
+        format!("`{system}` was already {state}.")
+    };
+
+    ctx.say(reply).await?;
+    Ok(())
+}
+
+async fn autocomplete_system(ctx: Context<'_>, partial: &str) -> Vec<String>
+{
+    ctx.data().registry.system_names()
+        .into_iter()
+        .filter(|name| name.contains(partial))
+        .map(str::to_owned)
+        .collect()
+}
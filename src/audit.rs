@@ -0,0 +1,83 @@
+use serenity::{
+    all::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage},
+    model::Timestamp,
+    prelude::*,
+};
+use poise::serenity_prelude as serenity;
+use anyhow::{Context as _, Result};
+
+use crate::{config::Config, group_system::{Action, StopReason}, store::{ModerationAction, Store}};
+
+/// Discord's limit on embed field values.
+const FIELD_LIMIT: usize = 1024;
+
+/// Writes a stopped event to the store and posts it to the guild's mod log channel.
+/// Events outside guilds, or without a user to pin them on, aren't audited.
+pub async fn record(ctx: &Context, config: &Config, store: &Store, reason: &StopReason)
+{
+    if let Err(why) = try_record(ctx, config, store, reason).await {
+        eprintln!("Error auditing {} by `{}`: {why:?}", reason.verdict.rule, reason.system);
+    }
+}
+
+async fn try_record(ctx: &Context, config: &Config, store: &Store, reason: &StopReason) -> Result<()>
+{
+    let subject = &reason.subject;
+    let (Some(guild_id), Some(user_id)) = (subject.guild_id, subject.user_id) else { return Ok(()) };
+
+    let action = ModerationAction {
+        guild_id,
+        channel_id: subject.channel_id,
+        message_id: subject.message_id,
+        user_id,
+        system: reason.system.to_owned(),
+        rule: reason.verdict.rule.to_owned(),
+        action: reason.verdict.action.to_string(),
+        created_at: Timestamp::now(),
+    };
+    let id = store.record_moderation_action(&action)?;
+
+    let Some(mod_log) = config.guild(guild_id).and_then(|guild| guild.channels.mod_log) else { return Ok(()) };
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Automated action: {}", action.action))
+        .color(config.colors.moderation)
+        .field("User", format!("<@{user_id}>"), true)
+        .field("Channel", format!("<#{}>", action.channel_id), true)
+        .field("Rule", format!("`{}` (by `{}`)", action.rule, action.system), true)
+        .field("Content", code_block(&subject.content), false)
+        .footer(CreateEmbedFooter::new(format!("Audit #{id}")))
+        .timestamp(action.created_at);
+
+    if let Ok(user) = user_id.to_user(ctx).await {
+        embed = embed.author(CreateEmbedAuthor::new(&user.name).icon_url(user.face()));
+    }
+
+    // the message itself is still around to check, unless this deleted it
+    if let (Some(message_id), false) = (subject.message_id, reason.verdict.action == Action::DeleteMessage) {
+        embed = embed.url(message_id.link(subject.channel_id, Some(guild_id)));
+    }
+
+    if !subject.attachments.is_empty() {
+        embed = embed.field("Attachments", truncate(&subject.attachments.join("\n"), FIELD_LIMIT), false);
+    }
+
+    mod_log.send_message(&ctx.http, CreateMessage::new().embed(embed)).await
+        .context("posting to the mod log")?;
+
+    Ok(())
+}
+
+fn code_block(content: &str) -> String
+{
+    if content.is_empty() { return "*(empty)*".to_owned(); }
+
+    // keep the fence intact and the block within the field limit
+    format!("```\n{}\n```", truncate(&content.replace("```", "`\u{200b}``"), FIELD_LIMIT - 8))
+}
+
+fn truncate(text: &str, limit: usize) -> String
+{
+    if text.chars().count() <= limit { return text.to_owned(); }
+    text.chars().take(limit - 1).chain(['…']).collect()
+}
@@ -0,0 +1,392 @@
+use std::{borrow::Cow, collections::{HashMap, HashSet}, net::SocketAddr, path::{Path, PathBuf}, time::Duration};
+
+use serde::Deserialize;
+use serenity::model::id::{ChannelId, EmojiId, GuildId, UserId};
+use poise::serenity_prelude as serenity;
+use anyhow::{anyhow, Context as _, Result};
+
+use crate::{reposts::RepostRules, rules::{PostRules, ResolutionRules}};
+
+/// Runtime configuration, loaded once at startup from a TOML file.
+#[derive(Debug)]
+pub struct Config
+{
+    pub database: PathBuf,
+    pub embed_timeout: Duration,
+    pub removal_dm_cooldown: Duration,
+    pub slow_system_budget: Duration,
+    pub metrics_addr: Option<SocketAddr>,
+    pub guilds: HashMap<GuildId, GuildConfig>,
+    pub colors: Colors,
+}
+
+/// On-disk layout: one `[[guild]]` table per served guild.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig
+{
+    #[serde(default = "default_database")]
+    database: PathBuf,
+
+    /// How long a freshly posted link waits for Discord to unfurl its embeds.
+    #[serde(default = "default_embed_timeout_ms")]
+    embed_timeout_ms: u64,
+
+    /// How long a user goes without another DM about a removed showcase message.
+    #[serde(default = "default_removal_dm_cooldown_secs")]
+    removal_dm_cooldown_secs: u64,
+
+    /// Systems taking longer than this per event get logged and counted in `/stats`.
+    #[serde(default = "default_slow_system_budget_ms")]
+    slow_system_budget_ms: u64,
+
+    /// Where to serve Prometheus metrics on `/metrics`. Off unless set; keep it on localhost.
+    metrics_addr: Option<SocketAddr>,
+
+    #[serde(default)]
+    guild: Vec<GuildConfig>,
+
+    #[serde(default)]
+    colors: Colors,
+}
+
+fn default_database() -> PathBuf { PathBuf::from("edward.db") }
+fn default_embed_timeout_ms() -> u64 { 2000 }
+fn default_slow_system_budget_ms() -> u64 { 250 }
+fn default_removal_dm_cooldown_secs() -> u64 { 600 }
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuildConfig
+{
+    pub id: GuildId,
+    pub channels: Channels,
+    pub emojis: Emojis,
+
+    #[serde(default)]
+    pub icons: Icons,
+
+    #[serde(default)]
+    pub blacklisted_reaction_users: HashSet<UserId>,
+
+    /// What each reaction adds to a post's score, keyed by custom emoji id or unicode emoji.
+    /// Reactions not listed don't count. Defaults to +1 for the upvote, 💙 and 😂, and -1 for the downvote.
+    #[serde(default)]
+    pub vote_weights: HashMap<String, i32>,
+
+    /// Sent to authors whose non-posts get removed from a showcase channel.
+    #[serde(default = "default_showcase_rules")]
+    pub showcase_rules: String,
+
+    /// What counts as a post, per showcase/vote channel. Unlisted channels use the defaults.
+    #[serde(default)]
+    pub post_rules: HashMap<ChannelId, PostRules>,
+
+    /// Minimum image sizes, per showcase/vote channel.
+    #[serde(default)]
+    pub resolution_rules: HashMap<ChannelId, ResolutionRules>,
+
+    /// Channels whose images are checked for reposts.
+    #[serde(default)]
+    pub repost_rules: HashMap<ChannelId, RepostRules>,
+}
+
+fn default_vote_weights(emojis: &Emojis) -> HashMap<String, i32>
+{
+    HashMap::from([
+        (emojis.upvote.to_string(), 1),
+        (emojis.downvote.to_string(), -1),
+        ("💙".to_owned(), 1),
+        ("😂".to_owned(), 1),
+    ])
+}
+
+fn default_showcase_rules() -> String
+{
+    "Showcase channels are for posts only: attach your screenshots or link them. \
+     Questions and chatter go in the regular channels.".to_owned()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Channels
+{
+    /// add vote reactions to posts and remove non-posts
+    #[serde(default)]
+    pub showcase: HashSet<ChannelId>,
+
+    /// add vote reactions to posts only
+    #[serde(default)]
+    pub vote: HashSet<ChannelId>,
+
+    /// showcase/vote channels whose posts each get a thread to discuss them in
+    #[serde(default)]
+    pub discussion_threads: HashSet<ChannelId>,
+
+    /// where failed systems and moderation actions get reported
+    pub mod_log: Option<ChannelId>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Emojis
+{
+    pub upvote: EmojiId,
+    pub downvote: EmojiId,
+}
+
+/// Images used by `/fetch`. Unset entries fall back to the guild's own icon.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Icons
+{
+    pub thumbnail: Option<String>,
+    pub banner: Option<String>,
+    pub avatar_fallback: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Colors
+{
+    /// `/fetch` title embed
+    pub header: u32,
+
+    /// `/fetch` post embeds
+    pub post: u32,
+
+    /// mod log embeds for automated actions
+    #[serde(default = "default_moderation_color")]
+    pub moderation: u32,
+}
+
+fn default_moderation_color() -> u32 { 0xE5484D }
+
+impl Default for Colors
+{
+    fn default() -> Self { Colors { header: 0x111A1F, post: 0xA175EB, moderation: default_moderation_color() } }
+}
+
+impl Config
+{
+    pub fn load(path: impl AsRef<Path>) -> Result<Self>
+    {
+        let path = path.as_ref();
+
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed reading config file `{}` (its path is the first argument, `edward.toml` by default)", path.display()))?;
+
+        let raw: RawConfig = toml::from_str(&raw)
+            .with_context(|| format!("failed parsing config file `{}`", path.display()))?;
+
+        Self::from_raw(raw)
+            .with_context(|| format!("invalid config file `{}`", path.display()))
+    }
+
+    fn from_raw(raw: RawConfig) -> Result<Self>
+    {
+        for (name, color) in [("header", raw.colors.header), ("post", raw.colors.post), ("moderation", raw.colors.moderation)] {
+            if color > 0xFFFFFF {
+                return Err(anyhow!("color `{name}` ({color:#X}) is not a 24-bit RGB value"));
+            }
+        }
+
+        let mut guilds = HashMap::with_capacity(raw.guild.len());
+        let mut seen_channels: HashMap<ChannelId, GuildId> = HashMap::new();
+
+        for mut guild in raw.guild {
+            guild.validate().with_context(|| format!("in guild {}", guild.id))?;
+
+            if guild.vote_weights.is_empty() { guild.vote_weights = default_vote_weights(&guild.emojis); }
+
+            for &channel_id in guild.channels.showcase.iter().chain(&guild.channels.vote) {
+                if let Some(other) = seen_channels.insert(channel_id, guild.id) {
+                    return Err(anyhow!("channel {channel_id} is listed under both guild {other} and guild {}", guild.id));
+                }
+            }
+
+            if let Some(duplicate) = guilds.insert(guild.id, guild) {
+                return Err(anyhow!("guild {} is configured more than once", duplicate.id));
+            }
+        }
+
+        Ok(Config {
+            database: raw.database,
+            embed_timeout: Duration::from_millis(raw.embed_timeout_ms),
+            removal_dm_cooldown: Duration::from_secs(raw.removal_dm_cooldown_secs),
+            slow_system_budget: Duration::from_millis(raw.slow_system_budget_ms),
+            metrics_addr: raw.metrics_addr,
+            guilds,
+            colors: raw.colors
+        })
+    }
+
+    pub fn guild(&self, guild_id: GuildId) -> Option<&GuildConfig>
+    {
+        self.guilds.get(&guild_id)
+    }
+}
+
+impl GuildConfig
+{
+    fn validate(&self) -> Result<()>
+    {
+        if let Some(id) = self.channels.showcase.intersection(&self.channels.vote).next() {
+            return Err(anyhow!("channel {id} is listed as both a showcase and a vote channel"));
+        }
+
+        if let Some(id) = self.channels.discussion_threads.iter().find(|&&id| !self.is_watched_channel(id)) {
+            return Err(anyhow!("discussion threads are set for channel {id}, which is not a showcase/vote channel"));
+        }
+
+        if let Some(id) = self.channels.mod_log.filter(|&id| self.is_watched_channel(id)) {
+            return Err(anyhow!("mod log channel {id} is also a showcase/vote channel"));
+        }
+
+        if let Some(id) = self.post_rules.keys().find(|&&id| !self.is_watched_channel(id)) {
+            return Err(anyhow!("post rules are set for channel {id}, which is not a showcase/vote channel"));
+        }
+
+        if let Some(id) = self.resolution_rules.keys().find(|&&id| !self.is_watched_channel(id)) {
+            return Err(anyhow!("resolution rules are set for channel {id}, which is not a showcase/vote channel"));
+        }
+
+        if let Some(id) = self.repost_rules.keys().find(|&&id| !self.is_watched_channel(id)) {
+            return Err(anyhow!("repost rules are set for channel {id}, which is not a showcase/vote channel"));
+        }
+
+        if self.emojis.upvote == self.emojis.downvote {
+            return Err(anyhow!("upvote and downvote emojis must differ (both are {})", self.emojis.upvote));
+        }
+
+        Ok(())
+    }
+
+    pub fn is_showcase_channel(&self, channel_id: ChannelId) -> bool
+    {
+        self.channels.showcase.contains(&channel_id)
+    }
+
+    pub fn is_vote_channel(&self, channel_id: ChannelId) -> bool
+    {
+        self.channels.vote.contains(&channel_id)
+    }
+
+    /// Showcase or vote channel.
+    pub fn is_watched_channel(&self, channel_id: ChannelId) -> bool
+    {
+        self.is_showcase_channel(channel_id) || self.is_vote_channel(channel_id)
+    }
+
+    /// Keyed like `store::emoji_key`. Zero for reactions that aren't votes.
+    pub fn vote_weight(&self, emoji: &str) -> i32
+    {
+        self.vote_weights.get(emoji).copied().unwrap_or(0)
+    }
+
+    pub fn post_rules(&self, channel_id: ChannelId) -> Cow<'_, PostRules>
+    {
+        self.post_rules.get(&channel_id).map_or_else(|| Cow::Owned(PostRules::default()), Cow::Borrowed)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const GUILD: &str = "
+        [[guild]]
+        id = 1
+        emojis = { upvote = 10, downvote = 11 }
+        [guild.channels]
+        showcase = [100]
+        vote = [200]
+    ";
+
+    fn load(toml: &str) -> Result<Config>
+    {
+        Config::from_raw(toml::from_str(toml).unwrap())
+    }
+
+    fn error(toml: &str) -> String
+    {
+        format!("{:#}", load(toml).unwrap_err())
+    }
+
+    #[test]
+    fn valid_configs_load_with_defaults()
+    {
+        let config = load(GUILD).unwrap();
+        let guild = config.guild(GuildId::new(1)).unwrap();
+
+        assert_eq!(config.database, PathBuf::from("edward.db"));
+        assert_eq!(guild.vote_weight("10"), 1);
+        assert_eq!(guild.vote_weight("11"), -1);
+        assert_eq!(guild.vote_weight("💙"), 1);
+        assert!(guild.is_showcase_channel(ChannelId::new(100)));
+        assert!(guild.is_vote_channel(ChannelId::new(200)));
+    }
+
+    #[test]
+    fn shipped_config_loads()
+    {
+        Config::load(concat!(env!("CARGO_MANIFEST_DIR"), "/edward.toml")).unwrap();
+    }
+
+    #[test]
+    fn guilds_and_channels_are_configured_once()
+    {
+        let other_guild = |id: u64, channel: u64| format!("
+            [[guild]]
+            id = {id}
+            emojis = {{ upvote = 10, downvote = 11 }}
+            channels = {{ vote = [{channel}] }}
+        ");
+
+        let twice = format!("{GUILD}{}", other_guild(1, 300));
+        assert!(error(&twice).contains("guild 1 is configured more than once"));
+
+        let shared = format!("{GUILD}{}", other_guild(2, 200));
+        assert!(error(&shared).contains("channel 200 is listed under both guild 1 and guild 2"));
+
+        let both = GUILD.replace("vote = [200]", "vote = [100]");
+        assert!(error(&both).contains("channel 100 is listed as both a showcase and a vote channel"));
+    }
+
+    #[test]
+    fn per_channel_settings_need_a_watched_channel()
+    {
+        for (table, what) in [
+            ("[guild.post_rules.300]", "post rules"),
+            ("[guild.resolution_rules.300]\nmin_width = 1920\nmin_height = 1080", "resolution rules"),
+            ("[guild.repost_rules.300]", "repost rules"),
+        ] {
+            let config = format!("{GUILD}\n{table}");
+            assert!(error(&config).contains(&format!("{what} are set for channel 300, which is not a showcase/vote channel")), "{what}");
+        }
+
+        let threads = GUILD.replace("vote = [200]", "vote = [200]\ndiscussion_threads = [300]");
+        assert!(error(&threads).contains("discussion threads are set for channel 300"));
+
+        let mod_log = GUILD.replace("vote = [200]", "vote = [200]\nmod_log = 200");
+        assert!(error(&mod_log).contains("mod log channel 200 is also a showcase/vote channel"));
+    }
+
+    #[test]
+    fn emojis_and_colors_are_checked()
+    {
+        let same_emojis = GUILD.replace("downvote = 11", "downvote = 10");
+        assert!(error(&same_emojis).contains("upvote and downvote emojis must differ (both are 10)"));
+
+        let too_bright = format!("[colors]\nheader = 0x1000000\npost = 0xA175EB\n{GUILD}");
+        assert!(error(&too_bright).contains("color `header` (0x1000000) is not a 24-bit RGB value"));
+    }
+
+    #[test]
+    fn errors_name_the_guild()
+    {
+        let same_emojis = GUILD.replace("downvote = 11", "downvote = 10");
+        assert!(error(&same_emojis).starts_with("in guild 1: "));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, sync::{Mutex, MutexGuard, PoisonError}, time::Duration};
+
+use tokio::{sync::oneshot, time::timeout};
+use serenity::model::{channel::Message, event::MessageUpdateEvent, id::MessageId};
+use poise::serenity_prelude as serenity;
+
+type Waiters = HashMap<MessageId, oneshot::Sender<MessageUpdateEvent>>;
+
+/// Messages waiting for Discord to unfurl their link embeds, which arrive as a MESSAGE_UPDATE.
+/// `Handler::message_update` feeds every update in through `resolve`.
+pub struct EmbedUpdates
+{
+    waiters: Mutex<Waiters>,
+    timeout: Duration,
+}
+
+impl EmbedUpdates
+{
+    pub fn new(timeout: Duration) -> Self
+    {
+        EmbedUpdates { waiters: Mutex::default(), timeout }
+    }
+
+    fn waiters(&self) -> MutexGuard<'_, Waiters>
+    {
+        self.waiters.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Hands an embed-carrying update to the message waiting for it, if any.
+    pub fn resolve(&self, event: &MessageUpdateEvent) -> bool
+    {
+        if event.embeds.as_ref().is_none_or(Vec::is_empty) { return false; }
+
+        match self.waiters().remove(&event.id) {
+            Some(waiter) => waiter.send(event.clone()).is_ok(),
+            None => false
+        }
+    }
+}
+
+/// Links in the content whose embeds haven't shown up yet.
+pub fn awaits_embeds(msg: &Message) -> bool
+{
+    msg.embeds.is_empty() && (msg.content.contains("https://") || msg.content.contains("http://"))
+}
+
+pub trait Debounce: Sized { async fn debounce(&mut self, updates: &EmbedUpdates); }
+impl Debounce for Message
+{
+    /// Waits for the embed update of a message with unresolved links, falling back to the
+    /// message as posted once the timeout runs out. Messages without links return immediately.
+    async fn debounce(&mut self, updates: &EmbedUpdates)
+    {
+        if !awaits_embeds(self) { return; }
+
+        let (waiter, update) = oneshot::channel();
+        updates.waiters().insert(self.id, waiter);
+
+        match timeout(updates.timeout, update).await {
+            Ok(Ok(event)) => event.apply_to_message(self),
+            _ => { updates.waiters().remove(&self.id); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::sync::Arc;
+
+    use super::*;
+    use super::serenity::Embed;
+
+    fn message(content: &str) -> Message
+    {
+        let mut msg = Message::default();
+        msg.id = MessageId::new(10);
+        msg.content = content.to_owned();
+        msg
+    }
+
+    fn update(id: u64, embeds: Vec<Embed>) -> MessageUpdateEvent
+    {
+        serde_json::from_value(serde_json::json!({
+            "id": id.to_string(),
+            "channel_id": "2",
+            "embeds": embeds,
+        })).unwrap()
+    }
+
+    fn embed(url: &str) -> Embed
+    {
+        serde_json::from_value(serde_json::json!({ "url": url })).unwrap()
+    }
+
+    /// Stands in for the gateway: delivers `events` to `resolve` after a short delay.
+    fn fake_gateway(updates: Arc<EmbedUpdates>, events: Vec<MessageUpdateEvent>) -> tokio::task::JoinHandle<Vec<bool>>
+    {
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            events.iter().map(|event| updates.resolve(event)).collect()
+        })
+    }
+
+    #[tokio::test]
+    async fn picks_up_the_unfurled_embeds()
+    {
+        let updates = Arc::new(EmbedUpdates::new(Duration::from_secs(5)));
+        let gateway = fake_gateway(updates.clone(), vec![
+            update(11, vec![embed("https://other.example")]),
+            update(10, vec![]),
+            update(10, vec![embed("https://tenor.com/view/cat")]),
+        ]);
+
+        let mut msg = message("https://tenor.com/view/cat");
+        msg.debounce(&updates).await;
+
+        assert_eq!(msg.embeds.len(), 1);
+        assert_eq!(msg.embeds[0].url.as_deref(), Some("https://tenor.com/view/cat"));
+        assert_eq!(gateway.await.unwrap(), vec![false, false, true]);
+        assert!(updates.waiters().is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_timeout()
+    {
+        let updates = Arc::new(EmbedUpdates::new(Duration::from_millis(5)));
+        let gateway = fake_gateway(updates.clone(), vec![update(10, vec![embed("https://example.com")])]);
+
+        let mut msg = message("look https://example.com");
+        msg.debounce(&updates).await;
+
+        assert!(msg.embeds.is_empty());
+        assert_eq!(gateway.await.unwrap(), vec![false]);
+        assert!(updates.waiters().is_empty());
+    }
+
+    #[tokio::test]
+    async fn messages_without_links_skip_the_wait()
+    {
+        let updates = EmbedUpdates::new(Duration::from_secs(60));
+
+        let mut msg = message("!rizz");
+        timeout(Duration::from_millis(100), msg.debounce(&updates)).await.unwrap();
+
+        assert!(updates.waiters().is_empty());
+    }
+}
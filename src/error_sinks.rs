@@ -0,0 +1,59 @@
+use serenity::{model::id::GuildId, async_trait, prelude::*};
+use poise::serenity_prelude as serenity;
+
+use crate::{config, group_system::{ErrorSink, SystemError}, shared, MESSAGE_LIMIT};
+
+/// The default sink: one line per failed system.
+pub struct Stderr;
+
+#[async_trait]
+impl ErrorSink for Stderr
+{
+    async fn report(&self, _: &Context, guild_id: Option<GuildId>, errors: &[SystemError])
+    {
+        let guild = guild_id.map_or_else(|| "no guild".to_owned(), |id| format!("guild {id}"));
+
+        for error in errors {
+            eprintln!("Error in {guild}: {error}");
+        }
+    }
+}
+
+/// Posts the errors to the guild's `channels.mod_log`, if it configured one.
+pub struct ModLog;
+
+#[async_trait]
+impl ErrorSink for ModLog
+{
+    async fn report(&self, ctx: &Context, guild_id: Option<GuildId>, errors: &[SystemError])
+    {
+        let config = shared::get::<config::Config>(&ctx.data).await;
+        let Some(channel_id) = guild_id.and_then(|id| config.guild(id)).and_then(|guild| guild.channels.mod_log) else { return };
+
+        let mut report = String::from("```\n");
+        for error in errors {
+            let line = format!("{error}\n");
+            if report.len() + line.len() + 3 > MESSAGE_LIMIT { break; }
+            report.push_str(&line);
+        }
+        report.push_str("```");
+
+        if let Err(why) = channel_id.say(&ctx.http, report).await {
+            eprintln!("Error reporting to mod log {channel_id}: {why:?}");
+        }
+    }
+}
+
+/// Hands the errors to every sink in turn.
+pub struct Fanout(pub Vec<Box<dyn ErrorSink>>);
+
+#[async_trait]
+impl ErrorSink for Fanout
+{
+    async fn report(&self, ctx: &Context, guild_id: Option<GuildId>, errors: &[SystemError])
+    {
+        for sink in &self.0 {
+            sink.report(ctx, guild_id, errors).await;
+        }
+    }
+}
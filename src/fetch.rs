@@ -1,31 +1,13 @@
 use poise::serenity_prelude as serenity;
-use poise::futures_util::StreamExt;
-use serenity::all::{CreateEmbed, Channel};
-use serenity::{
-    model::{channel::{Message, Channel::Guild, ReactionType::{Custom, Unicode}, MessageReaction}, id::ChannelId},
-    prelude::*,
-};
-use poise::CreateReply;
-use rayon::prelude::*;
-use anyhow::anyhow;
-
-use crate::Handler;
+use serenity::all::{ChannelType, CreateEmbed, GuildChannel};
+use poise::{ChoiceParameter, CreateReply};
 
-pub type Context<'a> = poise::Context<'a, Handler, anyhow::Error>;
+use crate::{index, ranking::{self, Sort}, window::{Period, Window}, Handler};
 
-#[derive(Debug, poise::ChoiceParameter)]
-enum ChannelOption
-{
-    #[name = "#🍙-showcase"] Showcase     = 0677869233803100171,
-    #[name = "#📷-wallpapers"] Wallpapers = 0964023097843937280,
-    #[name = "#📜-books"] Books           = 1294352242719068292,
-    #[name = "#😔-memes"] Memes              = 660353693283123231,
-    #[name = "#🌟-github-showcase"] GithubShowcase = 0788975142684459058,
-    #[name = "#⚜️-hall-of-fame"] HallOfFame = 1431695114807410809, 
-    #[name = "#media"] Media = 996403285667885197 ,
-}
+pub type Context<'a> = poise::Context<'a, Handler, anyhow::Error>;
 
 #[poise::command(slash_command)]
+#[allow(clippy::too_many_arguments)] // one per slash command option
 pub async fn fetch(
     ctx: Context<'_>,
     #[description = "Fetch top N posts"]
@@ -37,8 +19,35 @@ pub async fn fetch(
     lowest: Option<usize>,
 
     #[description = "Showcase channel to fetch posts from"]
-    channel: ChannelOption,
+    #[channel_types("Text", "Forum")]
+    channel: GuildChannel,
+
+    #[description = "How to rank posts (default: net score)"]
+    sort: Option<Sort>,
+
+    #[description = "Only posts from the last week, month or year"]
+    period: Option<Period>,
+
+    #[description = "Only posts from this date (2025-01-31), timestamp or message onwards"]
+    since: Option<String>,
+
+    #[description = "Only posts up to this date, or before this timestamp or message"]
+    until: Option<String>,
 ) -> Result<(), anyhow::Error> {
+    let config = ctx.data().config.clone();
+    let guild = match config.guild(channel.guild_id) {
+        Some(guild) => guild,
+        None => {
+            ctx.say("This server has no showcase channels configured.").await?;
+            return Ok(());
+        }
+    };
+
+    if !guild.is_showcase_channel(channel.id) && !guild.is_vote_channel(channel.id) {
+        ctx.say(format!("<#{}> is not a showcase or vote channel.", channel.id)).await?;
+        return Ok(());
+    }
+
     if top.is_some() && lowest.is_some() {
         ctx.say("You can only specify either `top` or `lowest`, not both!").await?;
         return Ok(());
@@ -46,9 +55,17 @@ pub async fn fetch(
         ctx.say("You must specify either one of `top` or `lowest`").await?;
         return Ok(());
     }
+
+    let window = match Window::new(period, since.as_deref(), until.as_deref(), serenity::Timestamp::now()) {
+        Ok(window) => window,
+        Err(why) => {
+            ctx.say(why).await?;
+            return Ok(());
+        }
+    };
     ctx.defer().await?;
 
-    let channel_id = ChannelId::new(channel as u64);
+    let channel_id = channel.id;
 
     // maybe just messages[(len - N)..]
     let (sorting_coefficient, num, reply_partitions) = if let Some(n) = top {
@@ -59,79 +76,90 @@ pub async fn fetch(
         return Ok(());
     };
 
-    let target_channel_name = match channel_id.to_channel(&ctx.http()).await {
-        Ok(channel) => {
-            match channel {
-                Channel::Guild(guild_channel) => guild_channel.name,
-                _ => unreachable!()
-            }
-        },
+    let target_channel_name = &channel.name;
 
-        Err(_) => unreachable!(/* this doesn't make sense - hence unreachable */)
-    };
+    let me = ctx.cache().current_user().id;
+    let store = ctx.data().store.clone();
+    if !store.is_backfilled(channel_id, window.since)? {
+        index::backfill(ctx.http(), &store, &channel, guild, window.since, me).await?;
+    }
 
-    let messages = capture_channel_posts(&ctx, channel_id, sorting_coefficient).await;
+    let mut posts = index::channel_posts(&store, channel_id, window, guild, me)?;
 
-    if messages.len() < num {
-        let guild_id = match channel_id.to_channel(&ctx.http()).await {
-            Ok(Guild(channel)) => channel.guild_id,
-            _ => return Err(anyhow!("how did we get here?")),
-        };
+    let sort = sort.unwrap_or_default();
+    ranking::rank(&mut posts, sort.ranking());
+    if sorting_coefficient == 1 { posts.reverse(); }
 
+    if posts.len() < num {
         let message_link = format!("https://discord.com/channels/{}/{}",
-            guild_id,
+            channel.guild_id,
             channel_id.get()
         );
 
-        ctx.say(format!("{} only has {} posts.", message_link, messages.len())).await?;
+        let in_window = if window == Window::default() { "" } else { " in that time window" };
+        ctx.say(format!("{} only has {} posts{in_window}.", message_link, posts.len())).await?;
         return Ok(());
     }
 
-    let mut embeds: Vec<CreateEmbed> = Vec::with_capacity(num);
-    embeds.push(CreateEmbed::new()
-        .title(format!("{} {} posts in #{}",
+    let guild_icon = channel.guild_id
+        .to_guild_cached(ctx.serenity_context())
+        .and_then(|g| g.icon_url());
+
+    let mut header = CreateEmbed::new()
+        .title(format!("{} {} posts in #{}{}",
             if sorting_coefficient == -1 { "Top" }
             else { "Lowest" },
             num,
-            target_channel_name
+            target_channel_name,
+            if sort == Sort::Net { String::new() } else { format!(" by {}", sort.name()) }
         ))
-        .thumbnail("https://cdn.discordapp.com/icons/647981638348832790/0449935cebf16998c890e0b16af0e6a0.webp")
-        .image("https://media.discordapp.net/attachments/647997874940018710/1370271088151367741/image.png?ex=681ee3e5&is=681d9265&hm=2c89755338a02761d570bc19fa8a7362bbad7db100646bed8ab9b02f92d6f7e9&=&format=webp")
-        .color(0x111A1F));
+        .color(config.colors.header);
+
+    if let Some(thumbnail) = guild.icons.thumbnail.as_ref().or(guild_icon.as_ref()) {
+        header = header.thumbnail(thumbnail);
+    }
+
+    if let Some(banner) = &guild.icons.banner {
+        header = header.image(banner);
+    }
 
-    for m in (0..num).map(|i| messages.get(i as usize)).flatten() {
+    let mut embeds: Vec<CreateEmbed> = Vec::with_capacity(num);
+    embeds.push(header);
+
+    for p in (0..num).filter_map(|i| posts.get(i)) {
+        // forum posts are indexed under their forum, but live in a thread of their own with the same id
+        let link_channel = if channel.kind == ChannelType::Forum { p.post.message_id.get() } else { p.post.channel_id.get() };
         let message_link = format!("https://discord.com/channels/{}/{}/{}",
-            match m.guild_id { Some(id) => format!("{id}"), None => "@me".to_owned() },
-            m.channel_id, m.id
+            p.post.guild_id, link_channel, p.post.message_id
         );
 
-        let message_content_trimmed = if m.content.len() > 256 { &format!("{}...", &m.content[0..253]) }
-            else { &m.content };
+        let message_content_trimmed = if p.post.content.chars().count() > 256 { &format!("{}...", p.post.content.chars().take(253).collect::<String>()) }
+            else { &p.post.content };
+
+        let user_pfp = p.author.as_ref().and_then(|author| author.avatar_url.clone())
+            .or_else(|| guild.icons.avatar_fallback.clone())
+            .or_else(|| guild_icon.clone());
 
-        let user_pfp = m.author.avatar_url()
-            .unwrap_or(
-                "https://cdn.discordapp.com/icons/647981638348832790/63e727f0267f9b2baf17b745650bf5f4.webp?size=4096"
-                    .to_string()
-            );
+        let resolution = match p.post.resolution {
+            Some((width, height)) => format!("\n📐 size •••• {width}x{height}"),
+            None => String::new(),
+        };
 
         let mut item = CreateEmbed::new()
             .title(message_content_trimmed)
-            .timestamp(m.timestamp)
-            .thumbnail(user_pfp)
-            .color(0xA175EB)
-            .description(format!("🪶 author •• {}\n💙 likes ••• {}\n🔗 link •••• {message_link}",
-                match &m.author.global_name { Some(name) => name, None => &m.author.name },
-                get_post_votes(&m)
+            .timestamp(p.post.created_at)
+            .color(config.colors.post)
+            .description(format!("🪶 author •• {}\n💙 likes ••• {}\n🔗 link •••• {message_link}{resolution}",
+                match &p.author { Some(author) => author.name.clone(), None => format!("<@{}>", p.post.author_id) },
+                p.votes.net()
             ));
 
-        for embed in &m.embeds {
-            if let Some(embed_img) = &embed.image {
-                item = item.image(&embed_img.url);
-            }
+        if let Some(user_pfp) = user_pfp {
+            item = item.thumbnail(user_pfp);
         }
-        
-        for attachment in &m.attachments {
-            item = item.image(&attachment.url);
+
+        for url in &p.post.image_urls {
+            item = item.image(url);
         }
 
         embeds.push(item);
@@ -147,54 +175,6 @@ pub async fn fetch(
 }
 
 
-async fn capture_channel_posts(ctx: &Context<'_>, channel_id: ChannelId, sorting_coefficient: isize) -> Vec<Message>
-{
-    let mut posts: Vec<Message> = vec![];
-    
-    let is_post = |r: &MessageReaction| -> bool {
-        match &r.reaction_type {
-            Custom { id: reaction_id, .. } => {
-                reaction_id.get() == 1343553189508681728
-            },
-            Unicode(emoji) => { (emoji == "💙") || (emoji == "😂") },
-            _ => unreachable!()
-        }
-    };
-
-    let mut message_iterator = channel_id.messages_iter(ctx.http()).boxed(); // boxed?
-    while let Some(Ok(m)) = message_iterator.next().await {
-        if m.reactions.iter().any(is_post) {
-            posts.push(m);
-        }
-    }
-
-    posts.par_sort_by_key(|m| sorting_coefficient * get_post_votes(&m));
-    posts
-}
-
-fn get_post_votes(m: &Message) -> isize
-{
-    let mut votes = 0isize;
-
-    for r in &m.reactions {
-        match &r.reaction_type {
-            Custom { name: Some(name), .. } => {
-                match name.as_str() {
-                    "upvote" => { votes += r.count as isize },
-                    "downvote" => { votes -= r.count as isize },
-                    _ => {}
-                }
-            },
-            Unicode(emoji) => {
-                if (emoji == "💙") || (emoji == "😂") { votes += r.count as isize }
-            }
-            _ => unreachable!()
-        }
-    }
-
-    votes
-}
-
 trait FallbackSlice<T>
 {
     fn fallback_slice(&self, start: usize, end: usize) -> &[T];
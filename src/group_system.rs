@@ -1,41 +1,454 @@
-use std::marker::PhantomData;
+use std::{fmt, future::Future, marker::PhantomData, ops::Deref, sync::{Arc, Mutex, MutexGuard, PoisonError}, time::{Duration, Instant}};
 
 use serenity::{
-    model::channel::{Message, Reaction},
+    model::{channel::{GuildChannel, Message, Reaction, ReactionType}, id::{ChannelId, GuildId, MessageId, UserId}},
+    cache::Cache,
+    async_trait,
     prelude::*,
 };
 use poise::serenity_prelude as serenity;
 
+use poise::futures_util::future::BoxFuture;
+
+use crate::{error_sinks, metrics::{self, Timed}, shared, toggles::{self, Disabled}};
+
 /// A processor takes a Data item (Message, Reaction) and processes it,
 /// allowing us to break down work into disjoint blocks.
+/// Static systems are read-only, so they always run concurrently with each other.
 pub trait StaticProcessor {
     type D: ProcessorData;
 
-    async fn process(&self, _: &Context, _: &<Self as StaticProcessor>::D) {}
+    async fn process(&self, _: &ReadOnlyCtx, _: &<Self as StaticProcessor>::D, _: &RunState) -> Result<(), SystemErrors> { Ok(()) }
+
+    /// Appends the tier's systems, in execution order.
+    fn systems(&self, _: &mut Vec<SystemInfo>) {}
 }
 
 pub trait DynamicProcessor {
     type D: ProcessorData;
 
-    async fn process(&self, _: &mut Context, _: &<Self as DynamicProcessor>::D) {}
+    async fn process(&self, _: &ReplyCtx, _: &<Self as DynamicProcessor>::D, _: &RunState) -> Result<(), SystemErrors> { Ok(()) }
+
+    /// Runs every system at once.
+    async fn process_concurrently(&self, _: &ReplyCtx, _: &<Self as DynamicProcessor>::D, _: &RunState) -> Result<(), SystemErrors> { Ok(()) }
+
+    fn systems(&self, _: &mut Vec<SystemInfo>) {}
 }
 
 pub trait ModerationProcessor {
     type D: ProcessorData;
 
-    async fn process(&self, _: &mut Context, _: &<Self as ModerationProcessor>::D) -> Propagation { Propagation::Propagate }
+    /// The system that stopped the event and why, if one did.
+    /// A failing moderation system stops the event too, without a verdict.
+    async fn process(&self, _: &ModCtx, _: &<Self as ModerationProcessor>::D, _: &RunState) -> Result<Option<(&'static str, Verdict)>, SystemErrors> { Ok(None) }
+
+    fn systems(&self, _: &mut Vec<SystemInfo>) {}
+}
+
+pub trait ProcessorData
+{
+    fn guild_id(&self) -> Option<GuildId>;
+
+    /// Where the event happened and who caused it, for the audit trail.
+    fn subject(&self) -> Subject;
+}
+
+impl ProcessorData for Message
+{
+    fn guild_id(&self) -> Option<GuildId> { self.guild_id }
+
+    fn subject(&self) -> Subject
+    {
+        Subject {
+            guild_id: self.guild_id,
+            channel_id: self.channel_id,
+            message_id: Some(self.id),
+            user_id: Some(self.author.id),
+            content: self.content.clone(),
+            attachments: self.attachments.iter().map(|attachment| attachment.url.clone()).collect(),
+        }
+    }
+}
+
+impl ProcessorData for Reaction
+{
+    fn guild_id(&self) -> Option<GuildId> { self.guild_id }
+
+    fn subject(&self) -> Subject
+    {
+        Subject {
+            guild_id: self.guild_id,
+            channel_id: self.channel_id,
+            message_id: Some(self.message_id),
+            user_id: self.user_id,
+            content: format!("reacted with {}", self.emoji),
+            attachments: vec![],
+        }
+    }
+}
+
+impl ProcessorData for ReactionRemoveAll
+{
+    fn guild_id(&self) -> Option<GuildId> { self.guild_id }
+
+    fn subject(&self) -> Subject
+    {
+        Subject {
+            guild_id: self.guild_id,
+            channel_id: self.channel_id,
+            message_id: Some(self.message_id),
+            user_id: None,
+            content: String::new(),
+            attachments: vec![],
+        }
+    }
+}
+
+impl ProcessorData for ReactionRemoveEmoji
+{
+    fn guild_id(&self) -> Option<GuildId> { self.0.guild_id }
+    fn subject(&self) -> Subject { self.0.subject() }
+}
+
+/// Every reaction was cleared off a message.
+pub struct ReactionRemoveAll
+{
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+}
+
+/// Every reaction of one emoji was cleared off a message. `user_id` is always `None`.
+pub struct ReactionRemoveEmoji(pub Reaction);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Propagation { Propagate, Stop(Verdict) }
+
+/// Why a moderation system stopped an event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Verdict
+{
+    /// Short snake_case name of the rule that fired.
+    pub rule: &'static str,
+    pub action: Action,
+}
+
+/// What a moderation system did about the event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action { DeleteMessage, RemoveReaction }
+
+impl fmt::Display for Action
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(match self { Action::DeleteMessage => "delete_message", Action::RemoveReaction => "remove_reaction" })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Subject
+{
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub message_id: Option<MessageId>,
+    pub user_id: Option<UserId>,
+
+    /// Taken from the event itself, so it outlives a deleted message.
+    pub content: String,
+    pub attachments: Vec<String>,
+}
+
+/// A stopped event, as `PriorityGroup::start` returns it for the audit trail.
+#[derive(Clone, Debug)]
+pub struct StopReason
+{
+    pub system: &'static str,
+    pub verdict: Verdict,
+    pub subject: Subject,
+}
+
+/// What a system is registered as. `name` is what errors are tagged with and what
+/// `/systems enable|disable` takes.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemInfo
+{
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// A failed system, tagged with the system's name.
+#[derive(Debug)]
+pub struct SystemError
+{
+    pub system: &'static str,
+    pub error: anyhow::Error,
+}
+
+pub type SystemErrors = Vec<SystemError>;
+
+
+impl fmt::Display for SystemError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "[{}] {:#}", self.system, self.error) }
+}
+
+impl SystemInfo
+{
+    fn error(&self, error: anyhow::Error) -> SystemError { SystemError { system: self.name, error } }
+
+    /// Merges the system's own result into the errors of the rest of its list.
+    fn collect(&self, head: anyhow::Result<()>, tail: Result<(), SystemErrors>) -> Result<(), SystemErrors>
+    {
+        match (head, tail) {
+            (Ok(()), tail) => tail,
+            (Err(error), Ok(())) => Err(vec![self.error(error)]),
+            (Err(error), Err(mut errors)) => {
+                errors.insert(0, self.error(error));
+                Err(errors)
+            }
+        }
+    }
+}
+
+/// What one `PriorityGroup::start` call threads through its tiers.
+pub struct RunState
+{
+    disabled: Arc<Disabled>,
+    timings: Mutex<Vec<(Timed, Duration)>>,
+}
+
+impl RunState
+{
+    fn timings(&self) -> MutexGuard<'_, Vec<(Timed, Duration)>>
+    {
+        self.timings.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs and times the system, unless it's disabled for the event's guild.
+    async fn system<T>(&self, info: &SystemInfo, skipped: T, system: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T>
+    {
+        if self.disabled.contains(info.name) { return Ok(skipped); }
+
+        let started = Instant::now();
+        let result = system.await;
+        self.timings().push((Timed::System(info.name), started.elapsed()));
+        result
+    }
+
+    /// Times the tier, if any of its systems ran.
+    async fn tier<T>(&self, tier: Tier, systems: impl Future<Output = T>) -> T
+    {
+        let ran = self.timings().len();
+        let started = Instant::now();
+        let result = systems.await;
+
+        if self.timings().len() > ran {
+            self.timings().push((Timed::Tier(tier), started.elapsed()));
+        }
+        result
+    }
+}
+
+/// Where `PriorityGroup::start` reports failed systems: logs, a mod channel, ...
+#[async_trait]
+pub trait ErrorSink: Send + Sync
+{
+    async fn report(&self, ctx: &Context, guild_id: Option<GuildId>, errors: &[SystemError]);
+}
+
+/// How the systems of a tier are scheduled relative to each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Execution { Sequential, Concurrent }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier { Moderation, Dynamic, Static }
+
+impl fmt::Display for Tier
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(match self { Tier::Moderation => "moderation", Tier::Dynamic => "dynamic", Tier::Static => "static" })
+    }
+}
+
+impl fmt::Display for Execution
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(match self { Execution::Sequential => "sequential", Execution::Concurrent => "concurrent" })
+    }
+}
+
+/// One tier of a `PriorityGroup`, as listed by `/systems list`.
+#[derive(Clone, Debug)]
+pub struct TierInfo
+{
+    pub tier: Tier,
+    pub execution: Execution,
+    pub systems: Vec<SystemInfo>,
+}
+
+/// Capability-scoped view of the serenity Context handed to static systems.
+/// Exposes no HTTP handle, so nothing here can touch Discord state:
+///
+/// ```compile_fail,E0599
+/// # use rhbot::group_system::ReadOnlyCtx;
+/// # use poise::serenity_prelude::Message;
+/// async fn static_system(ctx: &ReadOnlyCtx, msg: &Message) -> anyhow::Result<()>
+/// {
+///     Ok(ctx.delete_message(msg.channel_id, msg.id).await?)
+/// }
+/// ```
+///
+/// ```compile_fail,E0599
+/// # use rhbot::group_system::ReadOnlyCtx;
+/// # use poise::serenity_prelude::Message;
+/// async fn static_system(ctx: &ReadOnlyCtx, msg: &Message) -> anyhow::Result<()>
+/// {
+///     Ok(ctx.unreact(msg.channel_id, msg.id, '👍').await?)
+/// }
+/// ```
+///
+/// ```compile_fail,E0599
+/// # use rhbot::group_system::ReadOnlyCtx;
+/// # use poise::serenity_prelude::Message;
+/// async fn static_system(ctx: &ReadOnlyCtx, msg: &Message) -> anyhow::Result<()>
+/// {
+///     ctx.reply(msg, "nice rice").await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ReadOnlyCtx
+{
+    cache: Arc<Cache>,
+    data: Arc<RwLock<TypeMap>>,
+
+    /// Only reachable through `ReplyCtx` and `ModCtx`.
+    http: Arc<serenity::Http>,
+}
+
+impl ReadOnlyCtx
+{
+    pub fn cache(&self) -> &Arc<Cache> { &self.cache }
+    pub fn data(&self) -> &Arc<RwLock<TypeMap>> { &self.data }
+
+    /// `ReplyCtx::thread_parent` without the API fallback.
+    pub fn cached_thread_parent(&self, guild_id: GuildId, channel_id: ChannelId) -> Option<ChannelId>
+    {
+        self.cache().guild(guild_id)?.threads.iter().find(|thread| thread.id == channel_id)?.parent_id
+    }
+}
+
+/// Handed to dynamic systems: read-only access plus replying and reacting, but no deleting:
+///
+/// ```compile_fail,E0599
+/// # use rhbot::group_system::ReplyCtx;
+/// # use poise::serenity_prelude::Message;
+/// async fn dynamic_system(ctx: &ReplyCtx, msg: &Message) -> anyhow::Result<()>
+/// {
+///     Ok(ctx.delete_message(msg.channel_id, msg.id).await?)
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ReplyCtx(ReadOnlyCtx);
+
+impl Deref for ReplyCtx
+{
+    type Target = ReadOnlyCtx;
+    fn deref(&self) -> &ReadOnlyCtx { &self.0 }
+}
+
+impl ReplyCtx
+{
+    fn http(&self) -> &serenity::Http { &self.0.http }
+
+    pub async fn say(&self, channel_id: ChannelId, content: impl Into<String>) -> serenity::Result<Message>
+    {
+        channel_id.say(self.http(), content).await
+    }
+
+    pub async fn reply(&self, msg: &Message, content: impl Into<String>) -> serenity::Result<Message>
+    {
+        msg.reply(self.http(), content).await
+    }
+
+    pub async fn react(&self, channel_id: ChannelId, message_id: MessageId, reaction: impl Into<ReactionType>) -> serenity::Result<()>
+    {
+        channel_id.create_reaction(self.http(), message_id, reaction).await
+    }
+
+    /// Removes Edward's own reaction.
+    pub async fn unreact(&self, channel_id: ChannelId, message_id: MessageId, reaction: impl Into<ReactionType>) -> serenity::Result<()>
+    {
+        channel_id.delete_reaction(self.http(), message_id, None, reaction).await
+    }
+
+    /// Opens a public thread on a message.
+    pub async fn create_thread(&self, msg: &Message, name: impl Into<String>) -> serenity::Result<GuildChannel>
+    {
+        let thread = serenity::CreateThread::new(name).auto_archive_duration(serenity::AutoArchiveDuration::OneWeek);
+        msg.channel_id.create_thread_from_message(self.http(), msg.id, thread).await
+    }
+
+    /// The channel a thread belongs to, or `None` for anything that isn't a thread. Threads missing
+    /// from the cache, like one a message just unarchived, are looked up through the API.
+    pub async fn thread_parent(&self, guild_id: GuildId, channel_id: ChannelId) -> serenity::Result<Option<ChannelId>>
+    {
+        if self.cache().guild(guild_id).is_some_and(|guild| guild.channels.contains_key(&channel_id)) { return Ok(None); }
+        if let Some(parent) = self.cached_thread_parent(guild_id, channel_id) { return Ok(Some(parent)); }
+
+        Ok(channel_id.to_channel(self.http()).await?
+            .guild()
+            .filter(|channel| channel.thread_metadata.is_some())
+            .and_then(|thread| thread.parent_id))
+    }
 }
 
-pub trait ProcessorData {}
-impl ProcessorData for Message {}
-impl ProcessorData for Reaction {}
+/// Handed to moderation systems: everything a `ReplyCtx` can do, plus deleting.
+#[derive(Clone)]
+pub struct ModCtx(ReplyCtx);
+
+impl Deref for ModCtx
+{
+    type Target = ReplyCtx;
+    fn deref(&self) -> &ReplyCtx { &self.0 }
+}
+
+impl ModCtx
+{
+    pub fn new(ctx: &Context) -> Self
+    {
+        ModCtx(ReplyCtx(ReadOnlyCtx { cache: ctx.cache.clone(), data: ctx.data.clone(), http: ctx.http.clone() }))
+    }
+
+    pub async fn delete_message(&self, channel_id: ChannelId, message_id: MessageId) -> serenity::Result<()>
+    {
+        channel_id.delete_message(self.http(), message_id).await
+    }
+
+    /// Deletes a thread with all of its messages.
+    pub async fn delete_thread(&self, thread_id: ChannelId) -> serenity::Result<()>
+    {
+        thread_id.delete(self.http()).await.map(drop)
+    }
+
+    /// Removes someone else's reaction.
+    pub async fn delete_reaction(&self, reaction: &Reaction) -> serenity::Result<()>
+    {
+        reaction.delete(self.http()).await
+    }
 
-#[derive(PartialEq)]
-pub enum Propagation { Propagate, Stop }
+    /// DMs a user about something done to their content.
+    pub async fn direct_message(&self, user_id: UserId, content: impl Into<String>) -> serenity::Result<Message>
+    {
+        user_id.direct_message(self.http(), serenity::CreateMessage::new().content(content)).await
+    }
+}
 
-pub struct StaticProcessorList<F, Data: ProcessorData, Ps>(F, Ps) where Ps: StaticProcessor<D = Data>;
+pub struct StaticProcessorList<F, Data: ProcessorData, Ps>(SystemInfo, F, Ps) where Ps: StaticProcessor<D = Data>;
 impl<
-    F: AsyncFn (&Context, &Data),
+    F: AsyncFn (&ReadOnlyCtx, &Data) -> anyhow::Result<()>,
     Data: ProcessorData,
     Ps: StaticProcessor<D = Data>
 >
@@ -43,16 +456,23 @@ impl<
 {
     type D = Data;
 
-    async fn process(&self, ctx: &Context, data: &Data)
+    async fn process(&self, ctx: &ReadOnlyCtx, data: &Data, state: &RunState) -> Result<(), SystemErrors>
     {
-        self.0(ctx, data).await;
-        self.1.process(ctx, data).await;
+        let head = state.system(&self.0, (), self.1(ctx, data));
+        let (head, tail) = tokio::join!(head, self.2.process(ctx, data, state));
+        self.0.collect(head, tail)
+    }
+
+    fn systems(&self, systems: &mut Vec<SystemInfo>)
+    {
+        systems.push(self.0);
+        self.2.systems(systems);
     }
 }
 
-pub struct DynamicProcessorList<F, Data: ProcessorData, Ps>(F, Ps) where Ps: DynamicProcessor<D = Data>;
+pub struct DynamicProcessorList<F, Data: ProcessorData, Ps>(SystemInfo, F, Ps) where Ps: DynamicProcessor<D = Data>;
 impl<
-    F: AsyncFn (&mut Context, &Data),
+    F: AsyncFn (&ReplyCtx, &Data) -> anyhow::Result<()>,
     Data: ProcessorData,
     Ps: DynamicProcessor<D = Data>
 >
@@ -60,16 +480,29 @@ impl<
 {
     type D = Data;
 
-    async fn process(&self, ctx: &mut Context, data: &Data)
+    async fn process(&self, ctx: &ReplyCtx, data: &Data, state: &RunState) -> Result<(), SystemErrors>
+    {
+        let head = state.system(&self.0, (), self.1(ctx, data)).await;
+        self.0.collect(head, self.2.process(ctx, data, state).await)
+    }
+
+    async fn process_concurrently(&self, ctx: &ReplyCtx, data: &Data, state: &RunState) -> Result<(), SystemErrors>
     {
-        self.0(ctx, data).await;
-        self.1.process(ctx, data).await;
+        let head = state.system(&self.0, (), self.1(ctx, data));
+        let (head, tail) = tokio::join!(head, self.2.process_concurrently(ctx, data, state));
+        self.0.collect(head, tail)
+    }
+
+    fn systems(&self, systems: &mut Vec<SystemInfo>)
+    {
+        systems.push(self.0);
+        self.2.systems(systems);
     }
 }
 
-pub struct ModerationProcessorList<F, Data: ProcessorData, Ps>(F, Ps) where Ps: ModerationProcessor<D = Data>;
+pub struct ModerationProcessorList<F, Data: ProcessorData, Ps>(SystemInfo, F, Ps) where Ps: ModerationProcessor<D = Data>;
 impl<
-    F: AsyncFn (&mut Context, &Data) -> Propagation,
+    F: AsyncFn (&ModCtx, &Data) -> anyhow::Result<Propagation>,
     Data: ProcessorData,
     Ps: ModerationProcessor<D = Data>
 >
@@ -77,13 +510,21 @@ impl<
 {
     type D = Data;
 
-    async fn process(&self, ctx: &mut Context, data: &Data) -> Propagation
+    async fn process(&self, ctx: &ModCtx, data: &Data, state: &RunState) -> Result<Option<(&'static str, Verdict)>, SystemErrors>
     {
-        if self.0(ctx, data).await == Propagation::Propagate {
-            return self.1.process(ctx, data).await;
+        let head = state.system(&self.0, Propagation::Propagate, self.1(ctx, data)).await;
+
+        match head {
+            Ok(Propagation::Propagate) => self.2.process(ctx, data, state).await,
+            Ok(Propagation::Stop(verdict)) => Ok(Some((self.0.name, verdict))),
+            Err(error) => Err(vec![self.0.error(error)])
         }
+    }
 
-        Propagation::Stop
+    fn systems(&self, systems: &mut Vec<SystemInfo>)
+    {
+        systems.push(self.0);
+        self.2.systems(systems);
     }
 }
 
@@ -95,6 +536,7 @@ impl<Data: ProcessorData> ModerationProcessor for SentinelMessageProcessor<Data>
 
 /// Type-safe api for scheduling interaction systems.
 /// Execution order policy: Moderation systems -> Dynamic systems -> Static systems
+/// Each tier finishes before the next one starts, whatever the scheduling within it.
 pub struct PriorityGroup<
     Data: ProcessorData,
     ModerationProcessors: ModerationProcessor<D = Data>,
@@ -109,16 +551,28 @@ pub struct PriorityGroup<
 
     /// Read-only perms on the input Data.
     pub r#static: StaticProcessors,
+
+    /// Sequential unless opted into with `with_concurrent_dynamic_systems`.
+    pub dynamic_execution: Execution,
+
+    /// Stderr unless swapped out with `with_error_sink`.
+    pub error_sink: Arc<dyn ErrorSink>,
+
+    /// The event type the group handles, as its timings are labelled.
+    pub event: &'static str,
 }
 
 impl<Data: ProcessorData> PriorityGroup<Data, SentinelMessageProcessor<Data>, SentinelMessageProcessor<Data>, SentinelMessageProcessor<Data>>
 {
-    pub fn new() -> Self
+    pub fn new(event: &'static str) -> Self
     {
         PriorityGroup {
+            event,
             moderation: const { SentinelMessageProcessor(PhantomData) },
             dynamic: const { SentinelMessageProcessor(PhantomData) },
-            r#static: const { SentinelMessageProcessor(PhantomData) }
+            r#static: const { SentinelMessageProcessor(PhantomData) },
+            dynamic_execution: Execution::Sequential,
+            error_sink: Arc::new(error_sinks::Stderr),
         }
     }
 }
@@ -131,40 +585,346 @@ impl<
 >
     PriorityGroup<Data, ModerationProcessors, DynamicProcessors, StaticProcessors>
 {
-    pub fn with_moderation_system<F: AsyncFn (&mut Context, &Data) -> Propagation>(self, system: F)
+    /// Each `with_*_system` call puts the system at the front of its tier, so within a tier
+    /// systems run in the reverse of their registration order: register the one that has to go first last.
+    pub fn with_moderation_system<F: AsyncFn (&ModCtx, &Data) -> anyhow::Result<Propagation>>(self, info: SystemInfo, system: F)
         -> PriorityGroup<Data, ModerationProcessorList<F, Data, ModerationProcessors>, DynamicProcessors, StaticProcessors>
     {
         PriorityGroup {
-            moderation: ModerationProcessorList(system, self.moderation),
+            moderation: ModerationProcessorList(info, system, self.moderation),
             dynamic: self.dynamic,
-            r#static: self.r#static
+            r#static: self.r#static,
+            dynamic_execution: self.dynamic_execution,
+            error_sink: self.error_sink,
+            event: self.event,
         }
     }
 
-    pub fn with_dynamic_system<F: AsyncFn (&mut Context, &Data)>(self, system: F)
+    pub fn with_dynamic_system<F: AsyncFn (&ReplyCtx, &Data) -> anyhow::Result<()>>(self, info: SystemInfo, system: F)
         -> PriorityGroup<Data, ModerationProcessors, DynamicProcessorList<F, Data, DynamicProcessors>, StaticProcessors>
     {
         PriorityGroup {
             moderation: self.moderation,
-            dynamic: DynamicProcessorList(system, self.dynamic),
-            r#static: self.r#static
+            dynamic: DynamicProcessorList(info, system, self.dynamic),
+            r#static: self.r#static,
+            dynamic_execution: self.dynamic_execution,
+            error_sink: self.error_sink,
+            event: self.event,
         }
     }
 
-    pub fn with_static_system<F: AsyncFn (&Context, &Data)>(self, system: F)
+    pub fn with_static_system<F: AsyncFn (&ReadOnlyCtx, &Data) -> anyhow::Result<()>>(self, info: SystemInfo, system: F)
         -> PriorityGroup<Data, ModerationProcessors, DynamicProcessors, StaticProcessorList<F, Data, StaticProcessors>>
     {
         PriorityGroup {
             moderation: self.moderation,
             dynamic: self.dynamic,
-            r#static: StaticProcessorList(system, self.r#static),
+            r#static: StaticProcessorList(info, system, self.r#static),
+            dynamic_execution: self.dynamic_execution,
+            error_sink: self.error_sink,
+            event: self.event,
+        }
+    }
+
+    /// Dynamic systems that don't depend on each other's replies/reactions can opt into
+    /// running at once. Moderation still finishes first and static systems still wait.
+    pub fn with_concurrent_dynamic_systems(self) -> Self
+    {
+        PriorityGroup { dynamic_execution: Execution::Concurrent, ..self }
+    }
+
+    pub fn with_error_sink(self, error_sink: Arc<dyn ErrorSink>) -> Self
+    {
+        PriorityGroup { error_sink, ..self }
+    }
+
+    /// The group's tiers in execution order, each with its systems in execution order.
+    pub fn tiers(&self) -> Vec<TierInfo>
+    {
+        let mut moderation = vec![];
+        let mut dynamic = vec![];
+        let mut r#static = vec![];
+        self.moderation.systems(&mut moderation);
+        self.dynamic.systems(&mut dynamic);
+        self.r#static.systems(&mut r#static);
+
+        vec![
+            TierInfo { tier: Tier::Moderation, execution: Execution::Sequential, systems: moderation },
+            TierInfo { tier: Tier::Dynamic, execution: self.dynamic_execution, systems: dynamic },
+            TierInfo { tier: Tier::Static, execution: Execution::Concurrent, systems: r#static },
+        ]
+    }
+
+    /// Runs the tiers in order, skipping systems switched off for the event's guild,
+    /// records how long every system and tier took, and hands every failed system
+    /// to the error sink. Returns why the event was stopped, if a moderation system did.
+    pub async fn start(&self, ctx: Context, data: Data) -> Option<StopReason>
+    {
+        let state = RunState {
+            disabled: shared::get::<toggles::SystemToggles>(&ctx.data).await.disabled(data.guild_id()),
+            timings: Mutex::default(),
+        };
+
+        let result = self.run(&ModCtx::new(&ctx), &data, &state).await;
+        let timings = std::mem::take(&mut *state.timings());
+        shared::get::<metrics::Metrics>(&ctx.data).await.record(self.event, &timings);
+
+        match result {
+            Ok(stopped) => stopped,
+            Err(errors) => {
+                self.error_sink.report(&ctx, data.guild_id(), &errors).await;
+                None
+            }
+        }
+    }
+
+    async fn run(&self, ctx: &ModCtx, data: &Data, state: &RunState) -> Result<Option<StopReason>, SystemErrors>
+    {
+        let moderation = state.tier(Tier::Moderation, self.moderation.process(ctx, data, state)).await;
+        if let Some((system, verdict)) = moderation? {
+            return Ok(Some(StopReason { system, verdict, subject: data.subject() }));
+        }
+
+        let dynamic = state.tier(Tier::Dynamic, async {
+            match self.dynamic_execution {
+                Execution::Sequential => self.dynamic.process(ctx, data, state).await,
+                Execution::Concurrent => self.dynamic.process_concurrently(ctx, data, state).await,
+            }
+        }).await;
+
+        let r#static = state.tier(Tier::Static, self.r#static.process(ctx, data, state)).await;
+
+        match (dynamic, r#static) {
+            (Ok(()), Ok(())) => Ok(None),
+            (dynamic, r#static) => Err(dynamic.err().into_iter().chain(r#static.err()).flatten().collect())
+        }
+    }
+}
+
+/// A built `PriorityGroup` behind the event type it handles, so groups can be
+/// built once and stored. Made with `dispatch!`.
+pub struct Dispatch<Data>
+{
+    tiers: Vec<TierInfo>,
+    run: Box<dyn Fn(Context, Data) -> BoxFuture<'static, Option<StopReason>> + Send + Sync>,
+}
+
+impl<Data: ProcessorData> Dispatch<Data>
+{
+    pub fn new(tiers: Vec<TierInfo>, run: impl Fn(Context, Data) -> BoxFuture<'static, Option<StopReason>> + Send + Sync + 'static) -> Self
+    {
+        Dispatch { tiers, run: Box::new(run) }
+    }
+
+    pub async fn dispatch(&self, ctx: Context, data: Data) -> Option<StopReason> { (self.run)(ctx, data).await }
+
+    pub fn tiers(&self) -> &[TierInfo] { &self.tiers }
+}
+
+/// Erases a `PriorityGroup` into a `Dispatch`. A macro rather than a fn so the group's
+/// future is checked to be `Send` against its concrete type.
+macro_rules! dispatch {
+    ($group:expr) => {{
+        let group = std::sync::Arc::new($group);
+        $crate::group_system::Dispatch::new(group.tiers(), move |ctx, data| {
+            let group = group.clone();
+            Box::pin(async move { group.start(ctx, data).await })
+        })
+    }};
+}
+pub(crate) use dispatch;
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use tokio::{sync::Barrier, time::timeout};
+
+    /// Logs which systems saw it, in the order they ran.
+    struct Event
+    {
+        log: Mutex<Vec<&'static str>>,
+        barrier: Barrier,
+    }
+
+    impl Event
+    {
+        fn new() -> Self { Event { log: Mutex::default(), barrier: Barrier::new(2) } }
+        fn log(&self, system: &'static str) { self.log.lock().unwrap().push(system) }
+        fn logged(&self) -> Vec<&'static str> { self.log.lock().unwrap().clone() }
+    }
+
+    impl ProcessorData for Event
+    {
+        fn guild_id(&self) -> Option<GuildId> { None }
+
+        fn subject(&self) -> Subject
+        {
+            Subject { guild_id: None, channel_id: ChannelId::new(1), message_id: None, user_id: None, content: String::new(), attachments: vec![] }
         }
     }
 
-    pub async fn start(self, mut ctx: Context, data: Data)
+    const fn info(name: &'static str) -> SystemInfo { SystemInfo { name, description: "" } }
+
+    fn ctx() -> ModCtx
+    {
+        ModCtx(ReplyCtx(ReadOnlyCtx {
+            cache: Arc::new(Cache::new()),
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            http: Arc::new(serenity::Http::new("")),
+        }))
+    }
+
+    fn state() -> RunState { RunState { disabled: Arc::default(), timings: Mutex::default() } }
+
+    fn moderate(name: &'static str) -> impl AsyncFn(&ModCtx, &Event) -> anyhow::Result<Propagation>
+    {
+        async move |_, event| { event.log(name); Ok(Propagation::Propagate) }
+    }
+
+    fn reply(name: &'static str) -> impl AsyncFn(&ReplyCtx, &Event) -> anyhow::Result<()>
+    {
+        async move |_, event| { event.log(name); Ok(()) }
+    }
+
+    fn read(name: &'static str) -> impl AsyncFn(&ReadOnlyCtx, &Event) -> anyhow::Result<()>
+    {
+        async move |_, event| { event.log(name); Ok(()) }
+    }
+
+    /// Only finishes once another system is waiting on the barrier too.
+    fn meet(name: &'static str) -> impl AsyncFn(&ReplyCtx, &Event) -> anyhow::Result<()>
+    {
+        async move |_, event| { event.barrier.wait().await; event.log(name); Ok(()) }
+    }
+
+    const VERDICT: Verdict = Verdict { rule: "test_rule", action: Action::DeleteMessage };
+
+    fn stop(name: &'static str) -> impl AsyncFn(&ModCtx, &Event) -> anyhow::Result<Propagation>
+    {
+        async move |_, event| { event.log(name); Ok(Propagation::Stop(VERDICT)) }
+    }
+
+    fn fail(name: &'static str) -> impl AsyncFn(&ModCtx, &Event) -> anyhow::Result<Propagation>
     {
-        if self.moderation.process(&mut ctx, &data).await == Propagation::Stop { return; };
-        self.dynamic.process(&mut ctx, &data).await;
-        self.r#static.process(&ctx, &data).await;
+        async move |_, event| { event.log(name); Err(anyhow::anyhow!("{name} broke")) }
+    }
+
+    fn names(tier: &TierInfo) -> Vec<&'static str> { tier.systems.iter().map(|system| system.name).collect() }
+
+    #[tokio::test]
+    async fn tiers_run_moderation_then_dynamic_then_static()
+    {
+        let group = PriorityGroup::new("test")
+            .with_static_system(info("static"), read("static"))
+            .with_dynamic_system(info("dynamic"), reply("dynamic"))
+            .with_moderation_system(info("moderation"), moderate("moderation"));
+
+        let event = Event::new();
+        assert!(group.run(&ctx(), &event, &state()).await.unwrap().is_none());
+        assert_eq!(event.logged(), ["moderation", "dynamic", "static"]);
+
+        let tiers = group.tiers();
+        assert_eq!(tiers.iter().map(|tier| tier.tier).collect::<Vec<_>>(), [Tier::Moderation, Tier::Dynamic, Tier::Static]);
+    }
+
+    #[tokio::test]
+    async fn later_registrations_run_first_within_a_tier()
+    {
+        let group = PriorityGroup::new("test")
+            .with_moderation_system(info("moderation_a"), moderate("moderation_a"))
+            .with_moderation_system(info("moderation_b"), moderate("moderation_b"))
+            .with_dynamic_system(info("dynamic_a"), reply("dynamic_a"))
+            .with_dynamic_system(info("dynamic_b"), reply("dynamic_b"));
+
+        let event = Event::new();
+        group.run(&ctx(), &event, &state()).await.unwrap();
+        assert_eq!(event.logged(), ["moderation_b", "moderation_a", "dynamic_b", "dynamic_a"]);
+
+        // and they're listed the way they run
+        let tiers = group.tiers();
+        assert_eq!(names(&tiers[0]), ["moderation_b", "moderation_a"]);
+        assert_eq!(names(&tiers[1]), ["dynamic_b", "dynamic_a"]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_dynamic_systems_run_at_once_before_static_ones()
+    {
+        let group = PriorityGroup::new("test")
+            .with_dynamic_system(info("dynamic_a"), meet("dynamic_a"))
+            .with_dynamic_system(info("dynamic_b"), meet("dynamic_b"))
+            .with_concurrent_dynamic_systems()
+            .with_static_system(info("static"), read("static"));
+
+        let event = Event::new();
+        timeout(Duration::from_secs(5), group.run(&ctx(), &event, &state())).await
+            .expect("dynamic systems waited on each other")
+            .unwrap();
+
+        let logged = event.logged();
+        assert_eq!(logged.len(), 3);
+        assert_eq!(logged[2], "static");
+        assert_eq!(group.tiers()[1].execution, Execution::Concurrent);
+    }
+
+    #[tokio::test]
+    async fn sequential_dynamic_systems_run_one_at_a_time()
+    {
+        let group = PriorityGroup::new("test")
+            .with_dynamic_system(info("dynamic_a"), meet("dynamic_a"))
+            .with_dynamic_system(info("dynamic_b"), meet("dynamic_b"));
+
+        let event = Event::new();
+        assert!(timeout(Duration::from_millis(100), group.run(&ctx(), &event, &state())).await.is_err());
+        assert!(event.logged().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stops_name_their_system_and_skip_the_rest()
+    {
+        let group = PriorityGroup::new("test")
+            .with_moderation_system(info("after"), moderate("after"))
+            .with_moderation_system(info("stopper"), stop("stopper"))
+            .with_moderation_system(info("before"), moderate("before"))
+            .with_dynamic_system(info("dynamic"), reply("dynamic"))
+            .with_static_system(info("static"), read("static"));
+
+        let event = Event::new();
+        let stopped = group.run(&ctx(), &event, &state()).await.unwrap().expect("the event was stopped");
+
+        assert_eq!((stopped.system, stopped.verdict), ("stopper", VERDICT));
+        assert_eq!(stopped.subject.channel_id, ChannelId::new(1));
+        assert_eq!(event.logged(), ["before", "stopper"]);
+    }
+
+    #[tokio::test]
+    async fn failing_moderation_names_its_system_and_skips_the_rest()
+    {
+        let group = PriorityGroup::new("test")
+            .with_moderation_system(info("after"), moderate("after"))
+            .with_moderation_system(info("broken"), fail("broken"))
+            .with_dynamic_system(info("dynamic"), reply("dynamic"))
+            .with_static_system(info("static"), read("static"));
+
+        let event = Event::new();
+        let errors = group.run(&ctx(), &event, &state()).await.expect_err("the failure was reported");
+
+        assert_eq!(errors.iter().map(|error| error.system).collect::<Vec<_>>(), ["broken"]);
+        assert_eq!(errors[0].to_string(), "[broken] broken broke");
+        assert_eq!(event.logged(), ["broken"]);
+    }
+
+    #[tokio::test]
+    async fn disabled_stoppers_let_the_event_through()
+    {
+        let group = PriorityGroup::new("test")
+            .with_moderation_system(info("stopper"), stop("stopper"))
+            .with_static_system(info("static"), read("static"));
+
+        let state = RunState { disabled: Arc::new(["stopper".to_owned()].into()), timings: Mutex::default() };
+        let event = Event::new();
+
+        assert!(group.run(&ctx(), &event, &state).await.unwrap().is_none());
+        assert_eq!(event.logged(), ["static"]);
     }
 }
@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+
+use poise::futures_util::StreamExt;
+use serenity::{
+    model::{
+        channel::{ChannelType, GuildChannel, Message, Reaction, ReactionType, ThreadsData},
+        id::{ChannelId, GuildId, MessageId, UserId},
+        user::User,
+        Timestamp,
+    },
+    http::{Http, LightMethod, Request, Route},
+};
+use poise::serenity_prelude as serenity;
+use anyhow::{Context as _, Result};
+
+use crate::{config::{self, GuildConfig}, window::Window, group_system::{ReactionRemoveAll, ReactionRemoveEmoji, ReadOnlyCtx}, store::{self, emoji_key, PostRecord, Store, UserRecord}, shared, systems::{self, Placement}};
+
+/// A post as served by `/fetch`, straight out of the index.
+pub struct IndexedPost
+{
+    pub post: PostRecord,
+    pub author: Option<UserRecord>,
+    pub votes: Votes,
+}
+
+/// A post's weighted votes, plus every reaction it got whether it's a vote or not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Votes
+{
+    pub up: u64,
+    pub down: u64,
+    pub reactions: u64,
+}
+
+impl Votes
+{
+    pub fn net(&self) -> isize
+    {
+        self.up as isize - self.down as isize
+    }
+}
+
+/// StaticProcessor
+pub async fn index_post(ctx: &ReadOnlyCtx, msg: &Message) -> Result<()>
+{
+    if systems::is_own_message(ctx, msg) { return Ok(()); }
+
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = msg.guild_id.and_then(|id| config.guild(id)) else { return Ok(()) };
+
+    let parent = ctx.cached_thread_parent(guild.id, msg.channel_id);
+    let Some(Placement::Post(channel_id)) = systems::placement(guild, msg, parent) else { return Ok(()) };
+
+    let store = shared::get::<store::Store>(ctx.data()).await;
+
+    // edited into a non-post
+    if systems::check_post(msg, guild, channel_id).is_err() {
+        store.delete_post(msg.id).with_context(|| format!("removing post {} from the index", msg.id))?;
+        return Ok(());
+    }
+
+    let me = ctx.cache().current_user().id;
+    index_voted_post(&store, msg, channel_id, guild, me).with_context(|| format!("indexing post {}", msg.id))
+}
+
+fn index_voted_post(store: &Store, msg: &Message, channel_id: ChannelId, guild: &GuildConfig, me: UserId) -> Result<()>
+{
+    store.upsert_user(&user_record(&msg.author))?;
+    store.upsert_post(&post_record(msg, guild.id, channel_id))?;
+
+    // the cleaner seeded these before the post existed in the index,
+    // so their reaction_add events were dropped
+    store.add_vote(msg.id, &guild.emojis.upvote.to_string(), me)?;
+    store.add_vote(msg.id, &guild.emojis.downvote.to_string(), me)?;
+
+    Ok(())
+}
+
+/// StaticProcessor
+pub async fn record_vote(ctx: &ReadOnlyCtx, reaction: &Reaction) -> Result<()>
+{
+    let Some(user_id) = reaction.user_id else { return Ok(()) };
+
+    // other bots' reactions aren't votes; Edward's seeded ones are indexed but never counted
+    let is_bot = reaction.member.as_ref().map(|member| member.user.bot)
+        .or_else(|| ctx.cache().user(user_id).map(|user| user.bot))
+        .unwrap_or(false);
+    if is_bot && user_id != ctx.cache().current_user().id { return Ok(()); }
+
+    shared::get::<store::Store>(ctx.data()).await.add_vote(reaction.message_id, &emoji_key(&reaction.emoji), user_id)
+        .with_context(|| format!("recording vote on {}", reaction.message_id))?;
+
+    Ok(())
+}
+
+/// StaticProcessor
+///
+/// Edward's own seeded votes stay indexed when stripped: the restore systems put them back,
+/// and their reaction_add events may well arrive before this runs.
+pub async fn forget_vote(ctx: &ReadOnlyCtx, reaction: &Reaction) -> Result<()>
+{
+    let Some(user_id) = reaction.user_id else { return Ok(()) };
+    if user_id == ctx.cache().current_user().id { return Ok(()); }
+
+    shared::get::<store::Store>(ctx.data()).await.remove_vote(reaction.message_id, &emoji_key(&reaction.emoji), user_id)
+        .with_context(|| format!("forgetting vote on {}", reaction.message_id))?;
+
+    Ok(())
+}
+
+/// StaticProcessor
+pub async fn forget_votes(ctx: &ReadOnlyCtx, removed: &ReactionRemoveAll) -> Result<()>
+{
+    let me = ctx.cache().current_user().id;
+
+    shared::get::<store::Store>(ctx.data()).await.clear_votes(removed.message_id, None, Some(me))
+        .with_context(|| format!("forgetting votes on {}", removed.message_id))?;
+
+    Ok(())
+}
+
+/// StaticProcessor
+pub async fn forget_emoji_votes(ctx: &ReadOnlyCtx, ReactionRemoveEmoji(removed): &ReactionRemoveEmoji) -> Result<()>
+{
+    let me = ctx.cache().current_user().id;
+
+    shared::get::<store::Store>(ctx.data()).await.clear_votes(removed.message_id, Some(&emoji_key(&removed.emoji)), Some(me))
+        .with_context(|| format!("forgetting {} votes on {}", removed.emoji, removed.message_id))?;
+
+    Ok(())
+}
+
+pub fn forget_post(store: &Store, message_id: MessageId)
+{
+    if let Err(why) = store.delete_post(message_id) {
+        eprintln!("Error removing post {message_id} from the index: {why:?}");
+    }
+}
+
+/// Forum posts go with their thread. Threads opened on a message in a text channel share its id,
+/// but that message stays, and so does its post.
+pub fn forget_thread_post(store: &Store, thread_id: ChannelId, parent_kind: ChannelType)
+{
+    if parent_kind == ChannelType::Forum { forget_post(store, MessageId::new(thread_id.get())); }
+}
+
+/// Walks the channel's history back to `since`, or all of it for `None`, indexing every voted-on
+/// post and its voters. After this, the event systems above keep the channel's index current.
+/// `me` is Edward, whose own messages are never posts.
+pub async fn backfill(http: &Http, store: &Store, channel: &GuildChannel, guild: &GuildConfig, since: Option<Timestamp>, me: UserId) -> Result<usize>
+{
+    let indexed = match channel.kind {
+        ChannelType::Forum => backfill_forum(http, store, channel.id, guild, since, me).await?,
+        _ => {
+            let mut indexed = 0;
+
+            // newest first, so everything past `since` is older still
+            let mut message_iterator = channel.id.messages_iter(http).boxed();
+            while let Some(m) = message_iterator.next().await {
+                let m = m?;
+                if since.is_some_and(|since| m.timestamp < since) { break; }
+
+                if backfill_post(http, store, &m, channel.id, guild, me).await? { indexed += 1; }
+            }
+
+            indexed
+        }
+    };
+
+    store.mark_backfilled(channel.id, Timestamp::now(), since)?;
+    Ok(indexed)
+}
+
+/// Every thread's opening message is a post, archived threads included.
+async fn backfill_forum(http: &Http, store: &Store, forum_id: ChannelId, guild: &GuildConfig, since: Option<Timestamp>, me: UserId) -> Result<usize>
+{
+    const PAGE: u64 = 100;
+
+    let mut threads: Vec<GuildChannel> = guild.id.get_active_threads(http).await?.threads
+        .into_iter()
+        .filter(|thread| thread.parent_id == Some(forum_id))
+        .collect();
+
+    let mut before = None;
+    loop {
+        let page = archived_threads(http, forum_id, before, PAGE).await?;
+        before = page.threads.last().and_then(|thread| thread.thread_metadata?.archive_timestamp);
+        threads.extend(page.threads);
+
+        // threads are archived after they're created, so the rest were all created before `since`
+        let past_since = since.is_some_and(|since| before.is_some_and(|before| before < since));
+        if !page.has_more || before.is_none() || past_since { break; }
+    }
+
+    let mut indexed = 0;
+    for thread in threads.into_iter().filter(|thread| since.is_none_or(|since| thread.id.created_at() >= since)) {
+        let starter = match thread.id.message(http, MessageId::new(thread.id.get())).await {
+            Ok(starter) => starter,
+            // the opening message can be deleted out from under its thread
+            Err(serenity::Error::Http(why)) if why.status_code() == Some(serenity::StatusCode::NOT_FOUND) => continue,
+            Err(why) => return Err(why.into()),
+        };
+
+        if backfill_post(http, store, &starter, forum_id, guild, me).await? { indexed += 1; }
+    }
+
+    Ok(indexed)
+}
+
+/// Public threads archived before `before`, most recently archived first.
+/// `ChannelId::get_archived_public_threads` sends `before` as a number, where Discord wants a timestamp.
+async fn archived_threads(http: &Http, channel_id: ChannelId, before: Option<Timestamp>, limit: u64) -> Result<ThreadsData>
+{
+    let mut params = vec![("limit", limit.to_string())];
+    if let Some(before) = before { params.push(("before", before.to_string())); }
+
+    let request = Request::new(Route::ChannelArchivedPublicThreads { channel_id }, LightMethod::Get).params(Some(params));
+    Ok(http.fire(request).await?)
+}
+
+/// Indexes the message with its voters if it was voted on. `channel_id` is the forum for forum posts.
+async fn backfill_post(http: &Http, store: &Store, m: &Message, channel_id: ChannelId, guild: &GuildConfig, me: UserId) -> Result<bool>
+{
+    if !is_backfillable(m, channel_id, guild, me) { return Ok(false); }
+
+    // every reaction, not just the weighted ones, as `record_vote` keeps them live
+    let mut reactions = vec![];
+    for r in &m.reactions {
+        reactions.push((emoji_key(&r.reaction_type), reaction_users(http, m, &r.reaction_type).await?));
+    }
+
+    index_backfilled_post(store, m, channel_id, guild, &reactions)?;
+    Ok(true)
+}
+
+/// Voted-on posts `index_post` would take too, so `/fetch` doesn't depend on which of the two indexed a post.
+fn is_backfillable(m: &Message, channel_id: ChannelId, guild: &GuildConfig, me: UserId) -> bool
+{
+    m.reactions.iter().any(|r| is_vote_reaction(&r.reaction_type, guild))
+        && m.author.id != me
+        && systems::check_post(m, guild, channel_id).is_ok()
+}
+
+/// `reactions` are the message's emojis with everyone who reacted with them, bots included.
+fn index_backfilled_post(store: &Store, m: &Message, channel_id: ChannelId, guild: &GuildConfig, reactions: &[(String, Vec<User>)]) -> Result<()>
+{
+    store.upsert_user(&user_record(&m.author))?;
+    store.upsert_post(&post_record(m, guild.id, channel_id))?;
+
+    for (emoji, users) in reactions {
+        let voters: Vec<UserId> = users.iter().filter(|user| !user.bot).map(|user| user.id).collect();
+        store.replace_votes(m.id, emoji, &voters)?;
+    }
+
+    Ok(())
+}
+
+/// Every indexed post in the channel and window with its weighted votes, unsorted. `me` is Edward, whose seeded votes don't count.
+pub fn channel_posts(store: &Store, channel_id: ChannelId, window: Window, guild: &GuildConfig, me: UserId) -> Result<Vec<IndexedPost>>
+{
+    let mut counts: HashMap<MessageId, Vec<(String, u64)>> = HashMap::new();
+    for (message_id, emoji, count) in store.channel_vote_counts(channel_id, me)? {
+        counts.entry(message_id).or_default().push((emoji, count));
+    }
+
+    store.channel_posts(channel_id, window.since, window.until)?
+        .into_iter()
+        .map(|post| Ok(IndexedPost {
+            author: store.user(post.author_id)?,
+            votes: post_votes(counts.get(&post.message_id).map(Vec::as_slice).unwrap_or_default(), guild),
+            post,
+        }))
+        .collect()
+}
+
+fn post_votes(counts: &[(String, u64)], guild: &GuildConfig) -> Votes
+{
+    counts.iter().fold(Votes::default(), |mut votes, (emoji, count)| {
+        let weight = guild.vote_weight(emoji);
+        if weight > 0 { votes.up += weight as u64 * count }
+        else { votes.down += weight.unsigned_abs() as u64 * count }
+
+        votes.reactions += count;
+        votes
+    })
+}
+
+/// Reactions that mark a message as a voted-on post: the ones that raise its score.
+fn is_vote_reaction(reaction: &ReactionType, guild: &GuildConfig) -> bool
+{
+    guild.vote_weight(&emoji_key(reaction)) > 0
+}
+
+async fn reaction_users(http: &Http, m: &Message, reaction: &ReactionType) -> Result<Vec<User>>
+{
+    const PAGE: u8 = 100;
+    let mut users: Vec<User> = vec![];
+
+    loop {
+        let page = m.reaction_users(http, reaction.clone(), Some(PAGE), users.last().map(|u| u.id)).await?;
+        let done = page.len() < PAGE as usize;
+        users.extend(page);
+
+        if done { return Ok(users); }
+    }
+}
+
+fn post_record(msg: &Message, guild_id: GuildId, channel_id: ChannelId) -> PostRecord
+{
+    let image_urls = msg.embeds.iter()
+        .filter_map(|embed| embed.image.as_ref().map(|image| image.url.clone()))
+        .chain(msg.attachments.iter().map(|attachment| attachment.url.clone()))
+        .collect();
+
+    let resolution = msg.embeds.iter()
+        .filter_map(|embed| embed.image.as_ref().and_then(|image| image.width.zip(image.height)))
+        .chain(msg.attachments.iter().filter_map(|attachment| attachment.width.zip(attachment.height)))
+        .max_by_key(|&(width, height)| width as u64 * height as u64);
+
+    PostRecord {
+        message_id: msg.id,
+        guild_id,
+        channel_id,
+        author_id: msg.author.id,
+        content: msg.content.clone(),
+        image_urls,
+        resolution,
+        created_at: msg.timestamp,
+    }
+}
+
+fn user_record(user: &User) -> UserRecord
+{
+    UserRecord {
+        user_id: user.id,
+        name: user.global_name.clone().unwrap_or_else(|| user.name.clone()),
+        avatar_url: user.avatar_url(),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const CHANNEL: ChannelId = ChannelId::new(100);
+    const EDWARD: UserId = UserId::new(99);
+
+    fn guild() -> GuildConfig
+    {
+        toml::from_str(r#"
+            id = 1
+            channels = { showcase = [100] }
+            emojis = { upvote = 10, downvote = 11 }
+            vote_weights = { "10" = 1, "11" = -1 }
+        "#).unwrap()
+    }
+
+    fn message(id: u64) -> Message
+    {
+        let mut msg = Message::default();
+        msg.id = MessageId::new(id);
+        msg.channel_id = CHANNEL;
+        msg
+    }
+
+    fn user(id: UserId, bot: bool) -> User
+    {
+        let mut user = User::default();
+        user.id = id;
+        user.bot = bot;
+        user
+    }
+
+    #[test]
+    fn backfilled_and_live_indexed_posts_count_the_same()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(dir.path().join("edward.db")).unwrap();
+        let guild = guild();
+        let (alice, bob) = (UserId::new(7), UserId::new(8));
+
+        // upvotes from two people and Edward, a downvote, and a reaction that isn't a vote
+        let reactions = [
+            ("10", vec![alice, bob, EDWARD]),
+            ("11", vec![bob]),
+            ("🔥", vec![alice]),
+        ];
+
+        let backfilled = message(1);
+        let backfilled_reactions: Vec<(String, Vec<User>)> = reactions.iter()
+            .map(|(emoji, users)| (emoji.to_string(), users.iter().map(|&id| user(id, id == EDWARD)).collect()))
+            .collect();
+        index_backfilled_post(&store, &backfilled, CHANNEL, &guild, &backfilled_reactions).unwrap();
+
+        let live = message(2);
+        index_voted_post(&store, &live, CHANNEL, &guild, EDWARD).unwrap();
+        for (emoji, users) in &reactions {
+            for &user_id in users { store.add_vote(live.id, emoji, user_id).unwrap(); }
+        }
+
+        let posts = channel_posts(&store, CHANNEL, Window::default(), &guild, EDWARD).unwrap();
+        let votes = |id: MessageId| posts.iter().find(|post| post.post.message_id == id).unwrap().votes;
+
+        assert_eq!(votes(backfilled.id), Votes { up: 2, down: 1, reactions: 4 });
+        assert_eq!(votes(backfilled.id), votes(live.id));
+    }
+
+    fn upvoted(mut msg: Message) -> Message
+    {
+        msg.reactions = vec![serde_json::from_value(serde_json::json!({
+            "count": 1,
+            "count_details": { "burst": 0, "normal": 1 },
+            "me": false,
+            "me_burst": false,
+            "emoji": { "id": "10", "name": "upvote" },
+            "burst_colors": [],
+        })).unwrap()];
+        msg
+    }
+
+    #[test]
+    fn backfill_takes_the_posts_live_indexing_does()
+    {
+        let guild = guild();
+        let with_content = |content: &str| {
+            let mut msg = upvoted(message(1));
+            msg.content = content.to_owned();
+            msg
+        };
+
+        assert!(is_backfillable(&with_content("https://i.imgur.com/a.png"), CHANNEL, &guild, EDWARD));
+
+        // upvoted chatter is still chatter
+        assert!(!is_backfillable(&with_content("nice rice"), CHANNEL, &guild, EDWARD));
+
+        let mut own = with_content("https://i.imgur.com/a.png");
+        own.author.id = EDWARD;
+        assert!(!is_backfillable(&own, CHANNEL, &guild, EDWARD));
+
+        let mut unvoted = with_content("https://i.imgur.com/a.png");
+        unvoted.reactions.clear();
+        assert!(!is_backfillable(&unvoted, CHANNEL, &guild, EDWARD));
+    }
+
+    #[test]
+    fn only_forum_threads_take_their_post_with_them()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(dir.path().join("edward.db")).unwrap();
+        let guild = guild();
+
+        // a discussion thread opened on a post shares the post's id
+        let post = message(500);
+        index_voted_post(&store, &post, CHANNEL, &guild, EDWARD).unwrap();
+        let thread_id = ChannelId::new(post.id.get());
+
+        forget_thread_post(&store, thread_id, ChannelType::Text);
+        assert!(store.post(post.id).unwrap().is_some());
+
+        forget_thread_post(&store, thread_id, ChannelType::Forum);
+        assert!(store.post(post.id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,186 @@
+// the bot is these traits' only implementor and caller, so their futures' bounds are known
+#![allow(async_fn_in_trait)]
+
+use std::sync::Arc;
+
+use serenity::{
+    model::{channel::{ChannelType, GuildChannel, Message, PartialGuildChannel, Reaction}, event::MessageUpdateEvent, gateway::Ready, id::{ChannelId, GuildId, MessageId}},
+    gateway::ActivityData,
+    async_trait,
+    prelude::*,
+};
+use poise::serenity_prelude as serenity;
+
+use debounce::Debounce;
+
+pub mod admin;
+mod audit;
+pub mod config;
+pub mod debounce;
+pub mod download;
+pub mod error_sinks;
+pub mod fetch;
+pub mod group_system;
+mod index;
+pub mod metrics;
+pub mod notices;
+mod ranking;
+pub mod registry;
+mod reposts;
+mod rules;
+pub mod shared;
+pub mod store;
+mod systems;
+pub mod toggles;
+mod window;
+
+/// Discord's message length limit.
+pub const MESSAGE_LIMIT: usize = 2000;
+
+#[derive(Clone)]
+pub struct Handler
+{
+    pub config: Arc<config::Config>,
+    pub store: Arc<store::Store>,
+    pub embed_updates: Arc<debounce::EmbedUpdates>,
+    pub toggles: Arc<toggles::SystemToggles>,
+    pub metrics: Arc<metrics::Metrics>,
+    pub registry: Arc<registry::Registry>,
+}
+
+impl Handler
+{
+    async fn audit(&self, ctx: &Context, stopped: Option<group_system::StopReason>)
+    {
+        if let Some(reason) = stopped { audit::record(ctx, &self.config, &self.store, &reason).await; }
+    }
+
+    /// Whether the channel is a showcase/vote channel or one of their threads. Threads are
+    /// looked up in the cache only: this runs on every message event, before any system does.
+    fn is_watched(&self, ctx: &Context, guild_id: Option<GuildId>, channel_id: ChannelId) -> bool
+    {
+        let Some(guild) = guild_id.and_then(|id| self.config.guild(id)) else { return false };
+        if guild.is_watched_channel(channel_id) { return true; }
+
+        ctx.cache.guild(guild.id)
+            .and_then(|cached| cached.threads.iter().find(|thread| thread.id == channel_id)?.parent_id)
+            .is_some_and(|parent| guild.is_watched_channel(parent))
+    }
+}
+
+#[async_trait]
+impl EventHandler for Handler
+{
+    async fn ready(&self, ctx: Context, _: Ready)
+    {
+        ctx.set_activity(Some(
+            ActivityData::streaming("swatting flies in cisco's basement", "https://twitch.tv/zzz")
+                .expect("MAKE_STREAMING_STATUS")
+        ));
+    }
+
+    async fn message(&self, ctx: Context, mut msg: Message)
+    {
+        // only messages in watched channels and their threads, forum posts included, care about their link embeds
+        if self.is_watched(&ctx, msg.guild_id, msg.channel_id) { msg.debounce(&self.embed_updates).await; }
+
+        let stopped = self.registry.message.dispatch(ctx.clone(), msg).await;
+        self.audit(&ctx, stopped).await;
+    }
+
+    async fn message_update(&self, ctx: Context, old: Option<Message>, new: Option<Message>, event: MessageUpdateEvent)
+    {
+        // link unfurls of messages still being debounced are picked up by their `message` event
+        if self.embed_updates.resolve(&event) { return; }
+
+        // without a message cache, every other update would cost a REST fetch
+        if !self.is_watched(&ctx, event.guild_id, event.channel_id) { return; }
+
+        let Some(msg) = updated_message(&ctx, old, new, &event).await else { return };
+
+        let stopped = self.registry.message_update.dispatch(ctx.clone(), msg).await;
+        self.audit(&ctx, stopped).await;
+    }
+
+    async fn message_delete(&self, _: Context, _: ChannelId, deleted_message_id: MessageId, _: Option<GuildId>)
+    {
+        index::forget_post(&self.store, deleted_message_id);
+    }
+
+    async fn message_delete_bulk(&self, _: Context, _: ChannelId, deleted_message_ids: Vec<MessageId>, _: Option<GuildId>)
+    {
+        for message_id in deleted_message_ids { index::forget_post(&self.store, message_id); }
+    }
+
+    /// Forum posts go with their thread, without a message_delete of their own.
+    async fn thread_delete(&self, ctx: Context, thread: PartialGuildChannel, _: Option<GuildChannel>)
+    {
+        if let Some(parent_kind) = channel_kind(&ctx, thread.guild_id, thread.parent_id).await {
+            index::forget_thread_post(&self.store, thread.id, parent_kind);
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction)
+    {
+        let stopped = self.registry.reaction_add.dispatch(ctx.clone(), reaction).await;
+        self.audit(&ctx, stopped).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction)
+    {
+        self.registry.reaction_remove.dispatch(ctx, reaction).await;
+    }
+
+    async fn reaction_remove_all(&self, ctx: Context, channel_id: ChannelId, message_id: MessageId)
+    {
+        // the gateway doesn't send the guild along with this event; forum posts are in threads
+        let guild_id = ctx.cache.guilds().into_iter().find(|&guild_id| {
+            ctx.cache.guild(guild_id).is_some_and(|guild| {
+                guild.channels.contains_key(&channel_id) || guild.threads.iter().any(|thread| thread.id == channel_id)
+            })
+        });
+
+        self.registry.reaction_remove_all.dispatch(ctx, group_system::ReactionRemoveAll { guild_id, channel_id, message_id }).await;
+    }
+
+    async fn reaction_remove_emoji(&self, ctx: Context, removed_reactions: Reaction)
+    {
+        self.registry.reaction_remove_emoji.dispatch(ctx, group_system::ReactionRemoveEmoji(removed_reactions)).await;
+    }
+}
+
+/// The cache's idea of the channel's type, or a fresh fetch's.
+async fn channel_kind(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Option<ChannelType>
+{
+    let cached = ctx.cache.guild(guild_id).and_then(|guild| guild.channels.get(&channel_id).map(|channel| channel.kind));
+    if cached.is_some() { return cached; }
+
+    match channel_id.to_channel(&ctx.http).await {
+        Ok(channel) => channel.guild().map(|channel| channel.kind),
+        Err(why) => {
+            eprintln!("Error fetching channel {channel_id}: {why:?}");
+            None
+        }
+    }
+}
+
+/// Full message after an update: the cache's copy if it had one, otherwise a fresh fetch.
+async fn updated_message(ctx: &Context, old: Option<Message>, new: Option<Message>, event: &MessageUpdateEvent) -> Option<Message>
+{
+    if let Some(msg) = new { return Some(msg); }
+
+    let mut msg = match old {
+        Some(msg) => msg,
+        None => match event.channel_id.message(&ctx.http, event.id).await {
+            Ok(msg) => msg,
+            Err(why) => {
+                eprintln!("Error fetching edited message {}: {why:?}", event.id);
+                return None;
+            }
+        }
+    };
+
+    // REST messages don't carry their guild
+    event.apply_to_message(&mut msg);
+    Some(msg)
+}
@@ -1,42 +1,39 @@
+use std::sync::Arc;
+
 use obfstr::obfstr;
-use tokio::time::{sleep, Duration};
-use serenity::{
-    model::{channel::{Message, Reaction}, gateway::Ready},
-    gateway::ActivityData,
-    async_trait,
-    prelude::*,
-};
+use serenity::prelude::*;
 use poise::serenity_prelude as serenity;
 use anyhow::Result;
 
-mod download;
-mod fetch;
-mod group_system;
-mod systems;
-
-// add vote reactions to posts and remove non-posts
-const SHOWCASE_CHANNELS: [u64; 5] = [
-    0677869233803100171  /* #showcase */,
-    0964023097843937280  /* #wallpapers */,
-    1294352242719068292  /* #books */,
-    0788975142684459058  /* #github-showcase */,
-    1431695114807410809  /* #hall-of-fame */
-];
-
-// add vote reactions to posts only
-const VOTE_CHANNELS: [u64; 2] = [
-    0660353693283123231  /* #memes */,
-    996403285667885197   /* #media */
-];
+use rhbot::{admin, config, debounce, download, error_sinks, fetch, metrics, notices, registry, shared, store, toggles, Handler};
 
-const BLACKLISTED_REACTION_USERS: [u64; 0] = [
-];
-
-pub struct Handler;
+const DEFAULT_CONFIG_PATH: &str = "edward.toml";
 
 #[tokio::main]
 async fn main() -> Result<()>
 {
+    let config_path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_owned());
+    let config = config::Config::load(config_path)?;
+    let store = Arc::new(store::Store::open(&config.database)?);
+    let error_sink = Arc::new(error_sinks::Fanout(vec![Box::new(error_sinks::Stderr), Box::new(error_sinks::ModLog)]));
+    let handler = Handler {
+        embed_updates: Arc::new(debounce::EmbedUpdates::new(config.embed_timeout)),
+        toggles: Arc::new(toggles::SystemToggles::load(store.clone())?),
+        metrics: Arc::new(metrics::Metrics::new(config.slow_system_budget)),
+        store,
+        registry: Arc::new(registry::Registry::new(error_sink)),
+        config: Arc::new(config),
+    };
+
+    if let Some(addr) = handler.config.metrics_addr {
+        let metrics = handler.metrics.clone();
+        tokio::spawn(async move {
+            if let Err(why) = metrics::serve(metrics, addr).await {
+                eprintln!("Error serving metrics: {why:?}");
+            }
+        });
+    }
+
     let intents = GatewayIntents::GUILDS
         | GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
@@ -45,65 +42,27 @@ async fn main() -> Result<()>
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![fetch::fetch(), download::download()],
+            commands: vec![fetch::fetch(), download::download(), admin::systems(), admin::stats(), notices::removal_dms()],
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| Box::pin(async move {
-            poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-            Ok(Handler)
-        }))
+        .setup({
+            let handler = handler.clone();
+            move |ctx, _ready, framework| Box::pin(async move {
+                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                Ok(handler)
+            })
+        })
         .build();
 
     let client = serenity::ClientBuilder::new(obfstr!("TOKEN"), intents)
         .framework(framework)
-        .event_handler(Handler).await;
+        .type_map_insert::<shared::Key<config::Config>>(handler.config.clone())
+        .type_map_insert::<shared::Key<store::Store>>(handler.store.clone())
+        .type_map_insert::<shared::Key<toggles::SystemToggles>>(handler.toggles.clone())
+        .type_map_insert::<shared::Key<metrics::Metrics>>(handler.metrics.clone())
+        .type_map_insert::<shared::Key<notices::RemovalNotices>>(Arc::new(notices::RemovalNotices::new(handler.config.removal_dm_cooldown)))
+        .event_handler(handler).await;
 
     client?.start().await?;
     Ok(())
 }
-
-#[async_trait]
-impl EventHandler for Handler
-{
-    async fn ready(&self, ctx: Context, _: Ready)
-    {
-        ctx.set_activity(Some(
-            ActivityData::streaming("swatting flies in cisco's basement", "https://twitch.tv/zzz")
-                .expect("MAKE_STREAMING_STATUS")
-        ));
-    }
-
-    async fn message(&self, ctx: Context, mut msg: Message)
-    {
-        msg.debounce(&ctx).await;
-
-        group_system::PriorityGroup::new()
-            .with_moderation_system(systems::showcase_cleaner_and_voter)
-            .with_dynamic_system(systems::rizz_ping)
-            .start(ctx, msg)
-            .await;
-    }
-
-    async fn reaction_add(&self, ctx: Context, reaction: Reaction)
-    {
-        group_system::PriorityGroup::new()
-            .with_moderation_system(systems::block_blacklisted_reactors)
-            .start(ctx, reaction)
-            .await;
-    }
-}
-
-trait Debounce: Sized { async fn debounce(&mut self, ctx: &Context); }
-impl Debounce for Message
-{
-    async fn debounce(&mut self, ctx: &Context)
-    {
-        sleep(Duration::from_secs(2)).await;
-
-        match self.channel_id.message(&ctx.http, self.id).await
-        {
-            Ok(msg) => *self = msg,
-            Err(_) => return
-        }
-    }
-}
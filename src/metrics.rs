@@ -0,0 +1,208 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write as _,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+use anyhow::{Context as _, Result};
+
+use crate::group_system::Tier;
+
+/// How many of the latest runs the quantiles are taken over.
+const WINDOW: usize = 512;
+
+const QUANTILES: [f64; 3] = [0.5, 0.95, 0.99];
+
+/// What a duration was measured for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Timed
+{
+    Tier(Tier),
+    System(&'static str),
+}
+
+/// Durations of one system or tier, for one event type.
+#[derive(Default)]
+pub struct Series
+{
+    window: VecDeque<Duration>,
+    pub count: u64,
+    pub sum: Duration,
+    pub slow: u64,
+}
+
+impl Series
+{
+    fn push(&mut self, elapsed: Duration)
+    {
+        if self.window.len() == WINDOW { self.window.pop_front(); }
+        self.window.push_back(elapsed);
+        self.count += 1;
+        self.sum += elapsed;
+    }
+
+    /// Over the rolling window only.
+    pub fn quantile(&self, q: f64) -> Duration
+    {
+        let mut sorted: Vec<Duration> = self.window.iter().copied().collect();
+        sorted.sort_unstable();
+
+        match sorted.len() {
+            0 => Duration::ZERO,
+            len => sorted[((len - 1) as f64 * q).round() as usize],
+        }
+    }
+
+    pub fn max(&self) -> Duration { self.window.iter().copied().max().unwrap_or_default() }
+}
+
+/// Wall time of every system and tier `PriorityGroup::start` runs, keyed by event type.
+pub struct Metrics
+{
+    series: Mutex<BTreeMap<(&'static str, Timed), Series>>,
+
+    /// Systems slower than this get logged and counted.
+    budget: Duration,
+}
+
+impl Metrics
+{
+    pub fn new(budget: Duration) -> Self
+    {
+        Metrics { series: Mutex::default(), budget }
+    }
+
+    pub fn series(&self) -> MutexGuard<'_, BTreeMap<(&'static str, Timed), Series>>
+    {
+        self.series.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn record(&self, event: &'static str, timings: &[(Timed, Duration)])
+    {
+        let mut series = self.series();
+
+        for &(timed, elapsed) in timings {
+            let series = series.entry((event, timed)).or_default();
+            series.push(elapsed);
+
+            let Timed::System(system) = timed else { continue };
+            if elapsed > self.budget {
+                series.slow += 1;
+                eprintln!("Slow system `{system}` on {event}: took {elapsed:?}, budget is {:?}", self.budget);
+            }
+        }
+    }
+
+    /// Prometheus text exposition format, version 0.0.4.
+    pub fn render_prometheus(&self) -> String
+    {
+        let series = self.series();
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP edward_system_duration_seconds Wall time of each system, quantiles over the last {WINDOW} runs.");
+        let _ = writeln!(out, "# TYPE edward_system_duration_seconds summary");
+        for ((event, timed), series) in series.iter() {
+            let Timed::System(system) = timed else { continue };
+            write_summary(&mut out, "edward_system_duration_seconds", &format!("event=\"{event}\",system=\"{system}\""), series);
+        }
+
+        let _ = writeln!(out, "# HELP edward_tier_duration_seconds Wall time of each tier, quantiles over the last {WINDOW} runs.");
+        let _ = writeln!(out, "# TYPE edward_tier_duration_seconds summary");
+        for ((event, timed), series) in series.iter() {
+            let Timed::Tier(tier) = timed else { continue };
+            write_summary(&mut out, "edward_tier_duration_seconds", &format!("event=\"{event}\",tier=\"{tier}\""), series);
+        }
+
+        let _ = writeln!(out, "# HELP edward_slow_systems_total Runs of each system over the slow-system budget.");
+        let _ = writeln!(out, "# TYPE edward_slow_systems_total counter");
+        for ((event, timed), series) in series.iter() {
+            let Timed::System(system) = timed else { continue };
+            let _ = writeln!(out, "edward_slow_systems_total{{event=\"{event}\",system=\"{system}\"}} {}", series.slow);
+        }
+
+        out
+    }
+}
+
+fn write_summary(out: &mut String, name: &str, labels: &str, series: &Series)
+{
+    for q in QUANTILES {
+        let _ = writeln!(out, "{name}{{{labels},quantile=\"{q}\"}} {}", series.quantile(q).as_secs_f64());
+    }
+    let _ = writeln!(out, "{name}_sum{{{labels}}} {}", series.sum.as_secs_f64());
+    let _ = writeln!(out, "{name}_count{{{labels}}} {}", series.count);
+}
+
+/// Serves `render_prometheus` on `GET /metrics`. Meant to be bound to localhost for a scraper.
+pub async fn serve(metrics: Arc<Metrics>, addr: SocketAddr) -> Result<()>
+{
+    let listener = TcpListener::bind(addr).await
+        .with_context(|| format!("failed binding metrics endpoint to {addr}"))?;
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let metrics = metrics.clone();
+
+        tokio::spawn(async move {
+            let mut request = [0; 1024];
+            let read = stream.read(&mut request).await.unwrap_or_default();
+
+            let response = if request[..read].starts_with(b"GET /metrics ") {
+                let body = metrics.render_prometheus();
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned()
+            };
+
+            if let Err(why) = stream.write_all(response.as_bytes()).await {
+                eprintln!("Error serving metrics: {why:?}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn ms(n: u64) -> Duration { Duration::from_millis(n) }
+
+    #[test]
+    fn quantiles_cover_only_the_rolling_window()
+    {
+        let metrics = Metrics::new(ms(1000));
+        let old: Vec<_> = (0..WINDOW).map(|_| (Timed::System("index_post"), ms(900))).collect();
+        let new: Vec<_> = (1..=WINDOW as u64).map(|n| (Timed::System("index_post"), ms(n))).collect();
+        metrics.record("message", &old);
+        metrics.record("message", &new);
+
+        let series = metrics.series();
+        let series = &series[&("message", Timed::System("index_post"))];
+        assert_eq!(series.count, 2 * WINDOW as u64);
+        assert_eq!(series.quantile(0.5), ms(257));
+        assert_eq!(series.max(), ms(WINDOW as u64));
+        assert_eq!(series.slow, 0);
+    }
+
+    #[test]
+    fn slow_systems_are_counted_and_exposed()
+    {
+        let metrics = Metrics::new(ms(100));
+        metrics.record("message", &[
+            (Timed::System("showcase_cleaner_and_voter"), ms(250)),
+            (Timed::System("showcase_cleaner_and_voter"), ms(50)),
+            (Timed::Tier(Tier::Moderation), ms(300)),
+        ]);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("edward_slow_systems_total{event=\"message\",system=\"showcase_cleaner_and_voter\"} 1\n"));
+        assert!(text.contains("edward_system_duration_seconds_count{event=\"message\",system=\"showcase_cleaner_and_voter\"} 2\n"));
+        assert!(text.contains("edward_tier_duration_seconds{event=\"message\",tier=\"moderation\",quantile=\"0.99\"} 0.3\n"));
+        // tiers have no budget
+        assert!(!text.contains("edward_slow_systems_total{event=\"message\",system=\"moderation\"}"));
+    }
+}
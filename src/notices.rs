@@ -0,0 +1,143 @@
+use std::{collections::HashMap, sync::{Mutex, PoisonError}, time::{Duration, Instant}};
+
+use serenity::model::{channel::Message, id::{ChannelId, UserId}};
+use poise::serenity_prelude as serenity;
+use anyhow::Result;
+
+use crate::{config::GuildConfig, fetch, group_system::ModCtx, rules::Violation, shared, store, MESSAGE_LIMIT};
+
+/// When each user was last DMed about a removed message, so repeat offenders
+/// aren't DMed once per message.
+pub struct RemovalNotices
+{
+    cooldown: Duration,
+    last_sent: Mutex<HashMap<UserId, Instant>>,
+}
+
+impl RemovalNotices
+{
+    pub fn new(cooldown: Duration) -> Self
+    {
+        RemovalNotices { cooldown, last_sent: Mutex::default() }
+    }
+
+    /// Whether the user is off cooldown, starting a new one if so.
+    fn claim(&self, user_id: UserId) -> bool
+    {
+        let mut last_sent = self.last_sent.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+
+        if last_sent.get(&user_id).is_some_and(|&sent| now.duration_since(sent) < self.cooldown) { return false; }
+
+        last_sent.retain(|_, sent| now.duration_since(*sent) < self.cooldown);
+        last_sent.insert(user_id, now);
+        true
+    }
+}
+
+/// DMs the author of a message removed from a showcase channel why, what to do about it and
+/// what they wrote, so they can post it somewhere else. `channel_id` is the showcase channel,
+/// which for forum posts is the forum rather than the thread that went with the post.
+pub async fn notify_removed_message(ctx: &ModCtx, msg: &Message, channel_id: ChannelId, guild: &GuildConfig, violation: &Violation) -> Result<()>
+{
+    if msg.author.bot { return Ok(()); }
+    if shared::get::<store::Store>(ctx.data()).await.is_dm_opted_out(msg.author.id)? { return Ok(()); }
+    if !shared::get::<RemovalNotices>(ctx.data()).await.claim(msg.author.id) { return Ok(()); }
+
+    ctx.direct_message(msg.author.id, removal_notice(msg, channel_id, guild, violation)).await?;
+    Ok(())
+}
+
+fn removal_notice(msg: &Message, channel_id: ChannelId, guild: &GuildConfig, violation: &Violation) -> String
+{
+    let footer = "\n-# Don't want these? Use `/removal_dms enabled:False`.";
+    let mut notice = format!("Your message in <#{channel_id}> was removed: {violation}.\n{}\n", advice(violation, guild));
+
+    if !msg.content.is_empty() {
+        let room = MESSAGE_LIMIT.saturating_sub(notice.len() + footer.len() + 64);
+        let content: String = msg.content.replace("```", "`\u{200b}``").chars().take(room).collect();
+        notice.push_str(&format!("\nHere's what you wrote:\n```\n{content}\n```"));
+    }
+
+    notice.push_str(footer);
+    notice
+}
+
+/// What the author can do about it. Only messages that aren't posts at all get the channel's rules.
+fn advice<'a>(violation: &Violation, guild: &'a GuildConfig) -> &'a str
+{
+    match violation {
+        Violation::NotAPost => &guild.showcase_rules,
+        Violation::DisallowedAttachment { .. } | Violation::UnreadableImage { .. } => "You're welcome to post it again with only files the channel takes.",
+        Violation::MissingRequiredDomain | Violation::MissingGithubLink => "You're welcome to post it again with the link it needs.",
+        Violation::TooFewImages { .. } => "You're welcome to post it again with all of its images.",
+        Violation::LowResolution { .. } | Violation::WrongAspectRatio { .. } => "You're welcome to post it again in a size the channel takes.",
+        Violation::Repost { .. } => "Images already posted there are removed, so the channel stays fresh.",
+    }
+}
+
+/// Turn DMs about your removed showcase messages on or off.
+#[poise::command(slash_command)]
+pub async fn removal_dms(
+    ctx: fetch::Context<'_>,
+    #[description = "Whether Edward should DM you when it removes your message"]
+    enabled: bool,
+) -> Result<(), anyhow::Error> {
+    ctx.data().store.set_dm_opt_out(ctx.author().id, !enabled)?;
+
+    let reply = if enabled { "You'll get a DM when Edward removes one of your messages." }
+                else { "Edward won't DM you about removed messages anymore." };
+
+    ctx.send(poise::CreateReply::default().content(reply).ephemeral(true)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use poise::serenity_prelude::MessageId;
+
+    const FORUM: ChannelId = ChannelId::new(100);
+
+    fn guild() -> GuildConfig
+    {
+        toml::from_str("
+            id = 1
+            channels = { showcase = [100] }
+            emojis = { upvote = 10, downvote = 11 }
+            showcase_rules = 'Posts only.'
+        ").unwrap()
+    }
+
+    fn forum_post(content: &str) -> Message
+    {
+        let mut msg = Message::default();
+        msg.id = MessageId::new(500);
+        msg.channel_id = ChannelId::new(500);
+        msg.content = content.to_owned();
+        msg
+    }
+
+    #[test]
+    fn notices_point_at_the_channel_and_fit_the_violation()
+    {
+        let guild = guild();
+        let msg = forum_post("how do I get this bar?");
+
+        let not_a_post = removal_notice(&msg, FORUM, &guild, &Violation::NotAPost);
+        assert!(not_a_post.starts_with("Your message in <#100> was removed: it has no attachment or link.\nPosts only.\n"));
+        assert!(not_a_post.contains("```\nhow do I get this bar?\n```"));
+
+        let repost = removal_notice(&msg, FORUM, &guild, &Violation::Repost { link: "https://discord.com/channels/1/100/7".to_owned() });
+        assert!(repost.starts_with("Your message in <#100> was removed: it was already posted at https://discord.com/channels/1/100/7.\n"));
+        assert!(!repost.contains("Posts only."));
+    }
+
+    #[test]
+    fn long_messages_are_cut_to_fit()
+    {
+        let msg = forum_post(&"🍚".repeat(3000));
+        assert!(removal_notice(&msg, FORUM, &guild(), &Violation::NotAPost).chars().count() <= MESSAGE_LIMIT);
+    }
+}
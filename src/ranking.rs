@@ -0,0 +1,207 @@
+use rayon::prelude::*;
+
+use crate::index::IndexedPost;
+
+/// Seconds after which a post needs ten times the votes to stay level in `Hot`.
+const HOT_DECAY_SECS: f64 = 45_000.;
+
+/// Discord's epoch, 2015-01-01, which `Hot` measures post age from.
+const DISCORD_EPOCH: i64 = 1_420_070_400;
+
+/// z for a 95% confidence interval in `Wilson`.
+const WILSON_Z: f64 = 1.96;
+
+/// Orders posts for `/fetch`: higher scores come first in the top listing.
+pub trait Ranking: Sync
+{
+    fn score(&self, post: &IndexedPost) -> f64;
+}
+
+/// The `sort` choices offered by `/fetch`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Sort
+{
+    #[default]
+    #[name = "net score"]
+    Net,
+    #[name = "wilson score"]
+    Wilson,
+    #[name = "hot"]
+    Hot,
+    #[name = "controversial"]
+    Controversial,
+    #[name = "most reacted"]
+    MostReacted,
+}
+
+impl Sort
+{
+    pub fn ranking(self) -> &'static dyn Ranking
+    {
+        match self {
+            Sort::Net => &Net,
+            Sort::Wilson => &Wilson,
+            Sort::Hot => &Hot,
+            Sort::Controversial => &Controversial,
+            Sort::MostReacted => &MostReacted,
+        }
+    }
+}
+
+/// Upvotes minus downvotes.
+pub struct Net;
+impl Ranking for Net
+{
+    fn score(&self, post: &IndexedPost) -> f64
+    {
+        post.votes.net() as f64
+    }
+}
+
+/// Lower bound of the Wilson score interval for the share of upvotes, so a post needs
+/// both a good ratio and enough votes to be sure of it.
+pub struct Wilson;
+impl Ranking for Wilson
+{
+    fn score(&self, post: &IndexedPost) -> f64
+    {
+        let n = (post.votes.up + post.votes.down) as f64;
+        if n == 0. { return 0.; }
+
+        let p = post.votes.up as f64 / n;
+        let z2 = WILSON_Z * WILSON_Z;
+
+        (p + z2 / (2. * n) - WILSON_Z * ((p * (1. - p) + z2 / (4. * n)) / n).sqrt()) / (1. + z2 / n)
+    }
+}
+
+/// Reddit's hot: the order of magnitude of the net score plus how recent the post is,
+/// so newer posts outrank older ones with up to ten times their votes per `HOT_DECAY_SECS`.
+pub struct Hot;
+impl Ranking for Hot
+{
+    fn score(&self, post: &IndexedPost) -> f64
+    {
+        let net = post.votes.net();
+        let order = (net.unsigned_abs().max(1) as f64).log10();
+        let age = (post.post.created_at.unix_timestamp() - DISCORD_EPOCH) as f64;
+
+        net.signum() as f64 * order + age / HOT_DECAY_SECS
+    }
+}
+
+/// Reddit's controversial: lots of votes, split as evenly as possible.
+pub struct Controversial;
+impl Ranking for Controversial
+{
+    fn score(&self, post: &IndexedPost) -> f64
+    {
+        let (up, down) = (post.votes.up, post.votes.down);
+        if up == 0 || down == 0 { return 0.; }
+
+        let balance = up.min(down) as f64 / up.max(down) as f64;
+        ((up + down) as f64).powf(balance)
+    }
+}
+
+/// Every reaction counts, vote or not.
+pub struct MostReacted;
+impl Ranking for MostReacted
+{
+    fn score(&self, post: &IndexedPost) -> f64
+    {
+        post.votes.reactions as f64
+    }
+}
+
+/// Sorts best first. Ties keep their order.
+pub fn rank(posts: &mut [IndexedPost], ranking: &dyn Ranking)
+{
+    posts.par_sort_by(|a, b| ranking.score(b).total_cmp(&ranking.score(a)));
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use poise::serenity_prelude::{ChannelId, GuildId, MessageId, Timestamp, UserId};
+    use crate::{index::Votes, store::PostRecord};
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn post(id: u64, up: u64, down: u64, age_days: i64) -> IndexedPost
+    {
+        IndexedPost {
+            post: PostRecord {
+                message_id: MessageId::new(id),
+                guild_id: GuildId::new(1),
+                channel_id: ChannelId::new(2),
+                author_id: UserId::new(7),
+                content: format!("post {id}"),
+                image_urls: vec![],
+                resolution: None,
+                created_at: Timestamp::from_unix_timestamp(1_750_000_000 - age_days * DAY).unwrap(),
+            },
+            author: None,
+            votes: Votes { up, down, reactions: up + down },
+        }
+    }
+
+    fn ids(posts: &[IndexedPost]) -> Vec<u64>
+    {
+        posts.iter().map(|p| p.post.message_id.get()).collect()
+    }
+
+    #[test]
+    fn net_ranks_by_upvotes_minus_downvotes()
+    {
+        let mut posts = vec![post(1, 1, 0, 0), post(2, 50, 49, 0), post(3, 5, 0, 0)];
+        rank(&mut posts, Sort::Net.ranking());
+
+        // ties keep the order they came in
+        assert_eq!(ids(&posts), [3, 1, 2]);
+    }
+
+    #[test]
+    fn wilson_needs_votes_to_trust_a_ratio()
+    {
+        let mut posts = vec![post(1, 1, 0, 0), post(2, 50, 49, 0), post(3, 40, 2, 0), post(4, 0, 0, 0)];
+        rank(&mut posts, Sort::Wilson.ranking());
+
+        assert_eq!(ids(&posts), [3, 2, 1, 4]);
+        assert!((Wilson.score(&post(5, 40, 2, 0)) - 0.8424).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hot_lets_new_posts_beat_old_favourites()
+    {
+        let mut posts = vec![post(1, 100, 0, 30), post(2, 5, 0, 0), post(3, 5, 0, 2), post(4, 0, 3, 0)];
+        rank(&mut posts, Sort::Hot.ranking());
+
+        assert_eq!(ids(&posts), [2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn controversial_wants_big_even_splits()
+    {
+        let mut posts = vec![post(1, 50, 0, 0), post(2, 10, 9, 0), post(3, 50, 49, 0), post(4, 3, 3, 0)];
+        rank(&mut posts, Sort::Controversial.ranking());
+
+        assert_eq!(ids(&posts), [3, 2, 4, 1]);
+        assert_eq!(Controversial.score(&post(5, 50, 0, 0)), 0.);
+    }
+
+    #[test]
+    fn most_reacted_counts_every_reaction()
+    {
+        let mut quiet = post(1, 4, 0, 0);
+        let mut loud = post(2, 1, 1, 0);
+        quiet.votes.reactions = 4;
+        loud.votes.reactions = 12;
+
+        let mut posts = vec![quiet, loud];
+        rank(&mut posts, Sort::MostReacted.ranking());
+
+        assert_eq!(ids(&posts), [2, 1]);
+    }
+}
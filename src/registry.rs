@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use serenity::model::channel::{Message, Reaction};
+use poise::serenity_prelude as serenity;
+
+use crate::{group_system::{dispatch, Dispatch, ErrorSink, PriorityGroup, ReactionRemoveAll, ReactionRemoveEmoji, SystemInfo, TierInfo}, index, reposts, systems};
+
+const SHOWCASE_CLEANER_AND_VOTER: SystemInfo = SystemInfo {
+    name: "showcase_cleaner_and_voter",
+    description: "Adds vote reactions to showcase/vote posts and deletes non-posts in showcase channels and their threads",
+};
+
+const ENFORCE_WALLPAPER_RESOLUTION: SystemInfo = SystemInfo {
+    name: "enforce_wallpaper_resolution",
+    description: "Warns about or removes images under a channel's minimum resolution or off its aspect ratios",
+};
+
+const DETECT_REPOSTS: SystemInfo = SystemInfo {
+    name: "detect_reposts",
+    description: "Hashes image posts and points out or removes reposts of earlier ones",
+};
+
+const OPEN_DISCUSSION_THREAD: SystemInfo = SystemInfo {
+    name: "open_discussion_thread",
+    description: "Opens a thread to discuss each new post in channels with discussion threads",
+};
+
+const RIZZ_PING: SystemInfo = SystemInfo {
+    name: "rizz_ping",
+    description: "Replies to `!rizz`",
+};
+
+const INDEX_POST: SystemInfo = SystemInfo {
+    name: "index_post",
+    description: "Keeps the post index in sync with new and edited posts",
+};
+
+const BLOCK_BLACKLISTED_REACTORS: SystemInfo = SystemInfo {
+    name: "block_blacklisted_reactors",
+    description: "Removes reactions from blacklisted users",
+};
+
+const RECORD_VOTE: SystemInfo = SystemInfo {
+    name: "record_vote",
+    description: "Records the vote in the post index",
+};
+
+const RESTORE_VOTE_REACTION: SystemInfo = SystemInfo {
+    name: "restore_vote_reaction",
+    description: "Puts back Edward's vote reaction when it gets removed",
+};
+
+const FORGET_VOTE: SystemInfo = SystemInfo {
+    name: "forget_vote",
+    description: "Drops the vote from the post index",
+};
+
+const RESTORE_CLEARED_VOTE_REACTIONS: SystemInfo = SystemInfo {
+    name: "restore_cleared_vote_reactions",
+    description: "Puts back Edward's vote reactions after all reactions are cleared",
+};
+
+const FORGET_VOTES: SystemInfo = SystemInfo {
+    name: "forget_votes",
+    description: "Drops the cleared votes from the post index",
+};
+
+const RESTORE_CLEARED_VOTE_EMOJI: SystemInfo = SystemInfo {
+    name: "restore_cleared_vote_emoji",
+    description: "Puts back Edward's vote reaction after its emoji is cleared",
+};
+
+const FORGET_EMOJI_VOTES: SystemInfo = SystemInfo {
+    name: "forget_emoji_votes",
+    description: "Drops the cleared emoji's votes from the post index",
+};
+
+/// Every event's systems, wired up once at startup.
+pub struct Registry
+{
+    pub message: Dispatch<Message>,
+    pub message_update: Dispatch<Message>,
+    pub reaction_add: Dispatch<Reaction>,
+    pub reaction_remove: Dispatch<Reaction>,
+    pub reaction_remove_all: Dispatch<ReactionRemoveAll>,
+    pub reaction_remove_emoji: Dispatch<ReactionRemoveEmoji>,
+}
+
+impl Registry
+{
+    pub fn new(error_sink: Arc<dyn ErrorSink>) -> Self
+    {
+        // within a tier, systems run in the reverse of the order they're registered in
+        Registry {
+            message: dispatch!(PriorityGroup::new("message")
+                .with_moderation_system(DETECT_REPOSTS, reposts::detect_reposts)
+                .with_moderation_system(ENFORCE_WALLPAPER_RESOLUTION, systems::enforce_wallpaper_resolution)
+                // first, so nothing downloads or replies to messages that aren't posts
+                .with_moderation_system(SHOWCASE_CLEANER_AND_VOTER, systems::showcase_cleaner_and_voter)
+                .with_dynamic_system(RIZZ_PING, systems::rizz_ping)
+                .with_dynamic_system(OPEN_DISCUSSION_THREAD, systems::open_discussion_thread)
+                .with_concurrent_dynamic_systems()
+                .with_static_system(INDEX_POST, index::index_post)
+                .with_error_sink(error_sink.clone())),
+
+            // edits get the same moderation as new messages
+            message_update: dispatch!(PriorityGroup::new("message_update")
+                .with_moderation_system(DETECT_REPOSTS, reposts::detect_reposts)
+                .with_moderation_system(ENFORCE_WALLPAPER_RESOLUTION, systems::enforce_edited_wallpaper_resolution)
+                .with_moderation_system(SHOWCASE_CLEANER_AND_VOTER, systems::showcase_cleaner_and_voter)
+                .with_static_system(INDEX_POST, index::index_post)
+                .with_error_sink(error_sink.clone())),
+
+            reaction_add: dispatch!(PriorityGroup::new("reaction_add")
+                .with_moderation_system(BLOCK_BLACKLISTED_REACTORS, systems::block_blacklisted_reactors)
+                .with_static_system(RECORD_VOTE, index::record_vote)
+                .with_error_sink(error_sink.clone())),
+
+            reaction_remove: dispatch!(PriorityGroup::new("reaction_remove")
+                .with_dynamic_system(RESTORE_VOTE_REACTION, systems::restore_vote_reaction)
+                .with_static_system(FORGET_VOTE, index::forget_vote)
+                .with_error_sink(error_sink.clone())),
+
+            reaction_remove_all: dispatch!(PriorityGroup::new("reaction_remove_all")
+                .with_dynamic_system(RESTORE_CLEARED_VOTE_REACTIONS, systems::restore_cleared_vote_reactions)
+                .with_static_system(FORGET_VOTES, index::forget_votes)
+                .with_error_sink(error_sink.clone())),
+
+            reaction_remove_emoji: dispatch!(PriorityGroup::new("reaction_remove_emoji")
+                .with_dynamic_system(RESTORE_CLEARED_VOTE_EMOJI, systems::restore_cleared_vote_emoji)
+                .with_static_system(FORGET_EMOJI_VOTES, index::forget_emoji_votes)
+                .with_error_sink(error_sink)),
+        }
+    }
+
+    /// Each event type with its group's tiers, as wired above.
+    pub fn events(&self) -> [(&'static str, &[TierInfo]); 6]
+    {
+        [
+            ("message", self.message.tiers()),
+            ("message_update", self.message_update.tiers()),
+            ("reaction_add", self.reaction_add.tiers()),
+            ("reaction_remove", self.reaction_remove.tiers()),
+            ("reaction_remove_all", self.reaction_remove_all.tiers()),
+            ("reaction_remove_emoji", self.reaction_remove_emoji.tiers()),
+        ]
+    }
+
+    /// Names of every registered system, once each.
+    pub fn system_names(&self) -> Vec<&'static str>
+    {
+        let mut names: Vec<&'static str> = self.events()
+            .into_iter()
+            .flat_map(|(_, tiers)| tiers.iter().flat_map(|tier| tier.systems.iter().map(|system| system.name)))
+            .collect();
+
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{error_sinks, group_system::Tier};
+
+    fn moderation(tiers: &[TierInfo]) -> Vec<&'static str>
+    {
+        tiers.iter()
+            .filter(|tier| tier.tier == Tier::Moderation)
+            .flat_map(|tier| tier.systems.iter().map(|system| system.name))
+            .collect()
+    }
+
+    #[test]
+    fn edits_are_moderated_like_new_messages()
+    {
+        let registry = Registry::new(Arc::new(error_sinks::Stderr));
+
+        assert_eq!(moderation(registry.message.tiers()), ["showcase_cleaner_and_voter", "enforce_wallpaper_resolution", "detect_reposts"]);
+        assert_eq!(moderation(registry.message_update.tiers()), moderation(registry.message.tiers()));
+    }
+}
@@ -0,0 +1,233 @@
+use serde::Deserialize;
+use serenity::model::{channel::{Attachment, Message}, id::MessageId};
+use poise::serenity_prelude as serenity;
+use image::{imageops::FilterType, DynamicImage};
+use anyhow::{Context as _, Result};
+
+use crate::{config, group_system::{ModCtx, Propagation}, rules::Violation, shared, store::{self, ImageHash}, systems};
+
+/// Attachments bigger than this aren't downloaded for hashing.
+const MAX_HASHED_SIZE: u32 = 20 * 1024 * 1024;
+
+/// What the `image` features in Cargo.toml can decode. Animated gifs would only be hashed by
+/// their first frame, so they're left out.
+const HASHED_TYPES: [&str; 3] = ["image/png", "image/jpeg", "image/webp"];
+
+/// Repost detection for one showcase/vote channel. Images are compared against every
+/// channel in the guild that has this set, from when it was set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepostRules
+{
+    #[serde(default)]
+    pub action: RepostAction,
+
+    /// How many of the 64 hash bits may differ for two images to count as the same.
+    /// Recompression and resizing usually stay under 5; unrelated images land around 32.
+    #[serde(default = "default_max_distance")]
+    pub max_distance: u32,
+}
+
+fn default_max_distance() -> u32 { 6 }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepostAction
+{
+    /// Reply with a link to the original, which leaves the repost up.
+    #[default]
+    Reply,
+    Remove,
+}
+
+/// ModerationProcessor
+pub async fn detect_reposts(ctx: &ModCtx, msg: &Message) -> Result<Propagation>
+{
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = msg.guild_id.and_then(|id| config.guild(id)) else { return Ok(Propagation::Propagate) };
+    if guild.repost_rules.is_empty() { return Ok(Propagation::Propagate); }
+
+    let Some(channel_id) = systems::post_channel(ctx, guild, msg).await? else { return Ok(Propagation::Propagate) };
+    let Some(rules) = guild.repost_rules.get(&channel_id) else { return Ok(Propagation::Propagate) };
+
+    let store = shared::get::<store::Store>(ctx.data()).await;
+    let images = store.guild_image_hashes(guild.id)?;
+
+    // hashed when it went up or at an earlier edit, and pointed out then if it was a repost
+    if images.iter().any(|image| image.message_id == msg.id) { return Ok(Propagation::Propagate); }
+
+    let mut original = None;
+
+    for attachment in msg.attachments.iter().filter(|attachment| is_hashable(attachment)) {
+        // an image that won't download or decode is no reason to hold up the post
+        let hash = match hash_attachment(attachment).await {
+            Ok(hash) => hash,
+            Err(why) => {
+                eprintln!("Error hashing `{}` on message {}: {why:?}", attachment.filename, msg.id);
+                continue;
+            }
+        };
+
+        if original.is_none() {
+            original = find_original(&images, hash, rules.max_distance, msg.id);
+        }
+
+        store.add_image_hash(&ImageHash { message_id: msg.id, guild_id: guild.id, channel_id: msg.channel_id, hash })?;
+    }
+
+    let Some(original) = original else { return Ok(Propagation::Propagate) };
+    let link = original.message_id.link(original.channel_id, Some(guild.id));
+
+    match rules.action {
+        RepostAction::Remove => systems::remove_message(ctx, msg, channel_id, guild, Violation::Repost { link }).await,
+        RepostAction::Reply => {
+            ctx.reply(msg, format!("This looks like a repost of {link}")).await
+                .with_context(|| format!("pointing repost {} at {}", msg.id, original.message_id))?;
+            Ok(Propagation::Propagate)
+        }
+    }
+}
+
+fn is_hashable(attachment: &Attachment) -> bool
+{
+    attachment.size <= MAX_HASHED_SIZE
+        && attachment.content_type.as_deref().is_some_and(|content_type| {
+            let content_type = content_type.split(';').next().unwrap_or_default().trim();
+            HASHED_TYPES.iter().any(|hashed| content_type.eq_ignore_ascii_case(hashed))
+        })
+}
+
+async fn hash_attachment(attachment: &Attachment) -> Result<u64>
+{
+    let bytes = attachment.download().await?;
+
+    // decoding a 4k wallpaper takes long enough to hold up the runtime
+    tokio::task::spawn_blocking(move || hash_image(&bytes)).await?
+}
+
+/// Fails for files that aren't what their content type claims, or that the build can't decode.
+fn hash_image(bytes: &[u8]) -> Result<u64>
+{
+    Ok(dhash(&image::load_from_memory(bytes).context("decoding the image")?))
+}
+
+/// The earliest other message with an image within `max_distance` bits of `hash`.
+fn find_original(images: &[ImageHash], hash: u64, max_distance: u32, message_id: MessageId) -> Option<ImageHash>
+{
+    images.iter()
+        .filter(|image| image.message_id != message_id)
+        .find(|image| distance(image.hash, hash) <= max_distance)
+        .cloned()
+}
+
+/// Difference hash: each bit is whether a pixel of the 9x8 grayscale thumbnail is brighter than
+/// its right neighbour. Survives rescaling and recompression, not cropping.
+pub fn dhash(image: &DynamicImage) -> u64
+{
+    let thumbnail = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+
+    (0..8).flat_map(|y| (0..8).map(move |x| (x, y)))
+        .fold(0, |hash, (x, y)| (hash << 1) | (thumbnail.get_pixel(x, y)[0] > thumbnail.get_pixel(x + 1, y)[0]) as u64)
+}
+
+/// How many bits two hashes differ in.
+pub fn distance(a: u64, b: u64) -> u32
+{
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn scene(width: u32, height: u32) -> DynamicImage
+    {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let (u, v) = (x * 255 / width, y * 255 / height);
+            Rgb([(u * v / 255) as u8, (255 - u) as u8, ((u + v) / 2) as u8])
+        }))
+    }
+
+    #[test]
+    fn rescaled_copies_hash_close()
+    {
+        let original = dhash(&scene(1920, 1080));
+        let smaller = dhash(&scene(1920, 1080).resize_exact(640, 360, FilterType::Lanczos3));
+
+        assert!(distance(original, smaller) <= default_max_distance(), "{original:064b}\n{smaller:064b}");
+    }
+
+    #[test]
+    fn different_images_hash_apart()
+    {
+        let a = dhash(&scene(800, 600));
+        let b = dhash(&scene(800, 600).fliph());
+
+        assert!(distance(a, b) > default_max_distance(), "{a:064b}\n{b:064b}");
+    }
+
+    #[test]
+    fn originals_are_the_earliest_close_match_from_another_message()
+    {
+        let image = |message_id, hash| ImageHash {
+            message_id: MessageId::new(message_id),
+            guild_id: serenity::GuildId::new(1),
+            channel_id: serenity::ChannelId::new(2),
+            hash,
+        };
+        let images = vec![image(10, 0b1111_0000), image(11, 0b1111_0011), image(12, u64::MAX)];
+
+        assert_eq!(find_original(&images, 0b1111_0001, 2, MessageId::new(99)), Some(image(10, 0b1111_0000)));
+        assert_eq!(find_original(&images, 0b1111_0001, 2, MessageId::new(10)), Some(image(11, 0b1111_0011)));
+        assert_eq!(find_original(&images, 0b0000_1111, 2, MessageId::new(99)), None);
+    }
+
+    fn attachment(filename: &str, content_type: &str, size: u32) -> Attachment
+    {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "filename": filename,
+            "content_type": content_type,
+            "size": size,
+            "url": format!("https://cdn.discordapp.com/attachments/1/2/{filename}"),
+            "proxy_url": format!("https://media.discordapp.net/attachments/1/2/{filename}"),
+        })).unwrap()
+    }
+
+    #[test]
+    fn only_decodable_images_are_hashed()
+    {
+        assert!(is_hashable(&attachment("a.png", "image/png", 1024)));
+        assert!(is_hashable(&attachment("a.jpg", "image/jpeg", 1024)));
+        assert!(is_hashable(&attachment("a.webp", "image/webp", 1024)));
+
+        assert!(!is_hashable(&attachment("a.gif", "image/gif", 1024)));
+        assert!(!is_hashable(&attachment("a.avif", "image/avif", 1024)));
+        assert!(!is_hashable(&attachment("a.heic", "image/heic", 1024)));
+        assert!(!is_hashable(&attachment("a.mp4", "video/mp4", 1024)));
+        assert!(!is_hashable(&attachment("a.png", "image/png", MAX_HASHED_SIZE + 1)));
+    }
+
+    #[test]
+    fn unreadable_images_fail_to_hash()
+    {
+        let mut png = std::io::Cursor::new(vec![]);
+        scene(64, 48).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        assert_eq!(hash_image(png.get_ref()).unwrap(), dhash(&scene(64, 48)));
+
+        // truncated, and a format the build has no decoder for
+        assert!(hash_image(&png.get_ref()[..png.get_ref().len() / 2]).is_err());
+        assert!(hash_image(b"BM\x3a\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00").is_err());
+        assert!(hash_image(b"not an image").is_err());
+    }
+
+    #[test]
+    fn distance_counts_differing_bits()
+    {
+        assert_eq!(distance(0, 0), 0);
+        assert_eq!(distance(0b1011, 0b0001), 2);
+        assert_eq!(distance(0, u64::MAX), 64);
+    }
+}
@@ -0,0 +1,643 @@
+use std::fmt;
+
+use serde::Deserialize;
+use serenity::model::channel::Message;
+use poise::serenity_prelude as serenity;
+
+/// What makes a message in a showcase/vote channel count as a post.
+/// By default that's any attachment or embed, or text opening with an https link, unless every
+/// embed is a Discord emoji or tenor gif; thread messages are let through.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostRules
+{
+    /// MIME types, or `type/*` wildcards, attachments may have. Empty allows any.
+    #[serde(default)]
+    pub allowed_attachment_types: Vec<String>,
+
+    /// File extensions attachments may have, as in `png` or `.epub`. Empty allows any.
+    /// Checked alongside the type, so a renamed `.zip` doesn't pass as an image.
+    #[serde(default)]
+    pub allowed_extensions: Vec<String>,
+
+    /// Reject image attachments Discord couldn't read dimensions from, i.e. files that only claim to be images.
+    #[serde(default)]
+    pub check_image_dimensions: bool,
+
+    /// Only links to these domains (or their subdomains) count. Empty allows any.
+    #[serde(default)]
+    pub required_domains: Vec<String>,
+
+    /// Links to these never count. Entries may carry a path prefix, as in `cdn.discordapp.com/emojis`.
+    #[serde(default = "default_forbidden_domains")]
+    pub forbidden_domains: Vec<String>,
+
+    /// Image attachments plus embedded images.
+    #[serde(default)]
+    pub min_images: usize,
+
+    /// Lets plain chatter through in threads under the channel, like its discussion threads.
+    /// Off, thread messages have to be posts too.
+    #[serde(default = "default_allow_text_replies_in_threads")]
+    pub allow_text_replies_in_threads: bool,
+
+    /// For `#github-showcase` style channels: every post links a GitHub repo.
+    #[serde(default)]
+    pub require_github_link: bool,
+
+    /// Counts http(s) links anywhere in the text, and attachments sent along with a gif.
+    /// Off, the text has to open with an https link and messages embedding only emoji or gifs never count.
+    #[serde(default)]
+    pub loose_matching: bool,
+}
+
+fn default_allow_text_replies_in_threads() -> bool { true }
+
+fn default_forbidden_domains() -> Vec<String>
+{
+    vec!["cdn.discordapp.com/emojis".to_owned(), "tenor.com".to_owned()]
+}
+
+impl Default for PostRules
+{
+    fn default() -> Self
+    {
+        PostRules {
+            allowed_attachment_types: vec![],
+            allowed_extensions: vec![],
+            check_image_dimensions: false,
+            required_domains: vec![],
+            forbidden_domains: default_forbidden_domains(),
+            min_images: 0,
+            allow_text_replies_in_threads: default_allow_text_replies_in_threads(),
+            require_github_link: false,
+            loose_matching: false,
+        }
+    }
+}
+
+/// Size requirements for image attachments, as in a wallpapers channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolutionRules
+{
+    /// Compared long side to long side and short to short, so phone wallpapers pass too.
+    pub min_width: u32,
+    pub min_height: u32,
+
+    /// As in `16:9`, either orientation. Empty allows any.
+    #[serde(default)]
+    pub aspect_ratios: Vec<AspectRatio>,
+
+    #[serde(default)]
+    pub action: ResolutionAction,
+}
+
+/// What happens to a post with an image that breaks its channel's `ResolutionRules`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionAction
+{
+    /// Reply to the post, which stays up and gets voted on.
+    #[default]
+    Warn,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct AspectRatio
+{
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TryFrom<String> for AspectRatio
+{
+    type Error = String;
+
+    fn try_from(ratio: String) -> Result<Self, String>
+    {
+        let parsed = ratio.split_once(':')
+            .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+            .filter(|&(width, height)| width > 0 && height > 0);
+
+        match parsed {
+            Some((width, height)) => Ok(AspectRatio { width, height }),
+            None => Err(format!("`{ratio}` is not an aspect ratio like `16:9`")),
+        }
+    }
+}
+
+impl fmt::Display for AspectRatio
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+/// How far off an image's aspect ratio may be, relatively, and still match. "21:9" monitors are
+/// really 43:18 (3440x1440), while 1920x1200 is still no 16:9.
+const ASPECT_TOLERANCE: f64 = 0.03;
+
+/// The parts of a message the rules look at.
+#[derive(Debug, Default)]
+pub struct Candidate<'a>
+{
+    pub content: &'a str,
+    pub attachments: Vec<AttachmentInfo<'a>>,
+    pub embeds: Vec<EmbedInfo<'a>>,
+    pub in_thread: bool,
+}
+
+#[derive(Debug)]
+pub struct AttachmentInfo<'a>
+{
+    pub filename: &'a str,
+    pub content_type: Option<&'a str>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct EmbedInfo<'a>
+{
+    pub url: Option<&'a str>,
+    pub has_image: bool,
+}
+
+impl<'a> Candidate<'a>
+{
+    pub fn from_message(msg: &'a Message, in_thread: bool) -> Self
+    {
+        Candidate {
+            content: &msg.content,
+            attachments: msg.attachments.iter()
+                .map(|attachment| AttachmentInfo {
+                    filename: &attachment.filename,
+                    content_type: attachment.content_type.as_deref(),
+                    width: attachment.width,
+                    height: attachment.height,
+                })
+                .collect(),
+            embeds: msg.embeds.iter()
+                .map(|embed| EmbedInfo { url: embed.url.as_deref(), has_image: embed.image.is_some() || embed.thumbnail.is_some() })
+                .collect(),
+            in_thread,
+        }
+    }
+}
+
+/// Why a message isn't a post.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation
+{
+    NotAPost,
+    DisallowedAttachment { filename: String },
+    UnreadableImage { filename: String },
+    MissingRequiredDomain,
+    MissingGithubLink,
+    TooFewImages { required: usize, found: usize },
+    LowResolution { filename: String, width: u32, height: u32, min_width: u32, min_height: u32 },
+    WrongAspectRatio { filename: String, width: u32, height: u32, allowed: Vec<AspectRatio> },
+    Repost { link: String },
+}
+
+impl Violation
+{
+    /// The rule name moderation verdicts are recorded under.
+    pub fn rule(&self) -> &'static str
+    {
+        match self {
+            Violation::NotAPost => "not_a_post",
+            Violation::DisallowedAttachment { .. } => "attachment_type",
+            Violation::UnreadableImage { .. } => "image_dimensions",
+            Violation::MissingRequiredDomain => "required_domain",
+            Violation::MissingGithubLink => "github_link",
+            Violation::TooFewImages { .. } => "min_images",
+            Violation::LowResolution { .. } => "min_resolution",
+            Violation::WrongAspectRatio { .. } => "aspect_ratio",
+            Violation::Repost { .. } => "repost",
+        }
+    }
+}
+
+impl fmt::Display for Violation
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            Violation::NotAPost => write!(f, "it has no attachment or link"),
+            Violation::DisallowedAttachment { filename } => write!(f, "`{filename}` isn't a file type this channel takes"),
+            Violation::UnreadableImage { filename } => write!(f, "`{filename}` doesn't look like a real image"),
+            Violation::MissingRequiredDomain => write!(f, "it doesn't link any of the sites this channel takes"),
+            Violation::MissingGithubLink => write!(f, "it doesn't link a GitHub repository"),
+            Violation::TooFewImages { required, found } => write!(f, "it has {found} image(s), this channel needs at least {required}"),
+            Violation::LowResolution { filename, width, height, min_width, min_height } =>
+                write!(f, "`{filename}` is {width}x{height}, this channel needs at least {min_width}x{min_height}"),
+            Violation::Repost { link } => write!(f, "it was already posted at {link}"),
+            Violation::WrongAspectRatio { filename, width, height, allowed } => {
+                let allowed: Vec<String> = allowed.iter().map(AspectRatio::to_string).collect();
+                write!(f, "`{filename}` is {width}x{height}, this channel takes {} images", allowed.join(", "))
+            }
+        }
+    }
+}
+
+/// Checks a message against its channel's rules. `Ok` means it's a post.
+pub fn evaluate(rules: &PostRules, candidate: &Candidate) -> Result<(), Violation>
+{
+    if candidate.in_thread && rules.allow_text_replies_in_threads { return Ok(()); }
+
+    if let Some(attachment) = candidate.attachments.iter().find(|attachment| !attachment_allowed(rules, attachment)) {
+        return Err(Violation::DisallowedAttachment { filename: attachment.filename.to_owned() });
+    }
+
+    if rules.check_image_dimensions {
+        let unreadable = candidate.attachments.iter()
+            .find(|attachment| is_image(attachment) && (attachment.width.unwrap_or(0) == 0 || attachment.height.unwrap_or(0) == 0));
+
+        if let Some(attachment) = unreadable {
+            return Err(Violation::UnreadableImage { filename: attachment.filename.to_owned() });
+        }
+    }
+
+    let forbidden = |url: &str| rules.forbidden_domains.iter().any(|domain| matches_domain(url, domain));
+    let links: Vec<&str> = links(candidate.content)
+        .chain(candidate.embeds.iter().filter_map(|embed| embed.url))
+        .filter(|url| !forbidden(url))
+        .collect();
+
+    let counted_links = links.iter()
+        .filter(|url| rules.required_domains.is_empty() || rules.required_domains.iter().any(|domain| matches_domain(url, domain)))
+        .count();
+
+    // rich embeds without a url of their own still count, as they always have
+    let bare_embeds = candidate.embeds.iter().filter(|embed| embed.url.is_none()).count();
+
+    let counted = candidate.attachments.len() + counted_links + bare_embeds;
+
+    if rules.loose_matching {
+        if counted == 0 { return Err(if links.is_empty() { Violation::NotAPost } else { Violation::MissingRequiredDomain }); }
+    } else {
+        let only_forbidden_embeds = !candidate.embeds.is_empty() && candidate.embeds.iter().all(|embed| embed.url.is_some_and(forbidden));
+        let anything = !candidate.attachments.is_empty() || !candidate.embeds.is_empty() || candidate.content.starts_with("https://");
+
+        if !anything || only_forbidden_embeds { return Err(Violation::NotAPost); }
+        if !rules.required_domains.is_empty() && counted == 0 { return Err(Violation::MissingRequiredDomain); }
+    }
+
+    if rules.require_github_link && !links.iter().any(|url| matches_domain(url, "github.com")) {
+        return Err(Violation::MissingGithubLink);
+    }
+
+    let images = candidate.attachments.iter().filter(|attachment| is_image(attachment)).count()
+        + candidate.embeds.iter()
+            .filter(|embed| embed.has_image && embed.url.is_none_or(|url| links.contains(&url)))
+            .count();
+
+    if images < rules.min_images {
+        return Err(Violation::TooFewImages { required: rules.min_images, found: images });
+    }
+
+    Ok(())
+}
+
+/// Checks every image attachment Discord read dimensions from against the channel's size rules.
+pub fn check_resolution(rules: &ResolutionRules, attachments: &[AttachmentInfo]) -> Result<(), Violation>
+{
+    for attachment in attachments.iter().filter(|attachment| is_image(attachment)) {
+        let (Some(width), Some(height)) = (attachment.width, attachment.height) else { continue };
+        let filename = attachment.filename.to_owned();
+
+        let (long, short) = (width.max(height), width.min(height));
+        if long < rules.min_width.max(rules.min_height) || short < rules.min_width.min(rules.min_height) {
+            return Err(Violation::LowResolution { filename, width, height, min_width: rules.min_width, min_height: rules.min_height });
+        }
+
+        let ratio = long as f64 / short as f64;
+        let matches = |allowed: &AspectRatio| {
+            let allowed = allowed.width.max(allowed.height) as f64 / allowed.width.min(allowed.height) as f64;
+            (ratio - allowed).abs() / allowed <= ASPECT_TOLERANCE
+        };
+
+        if !rules.aspect_ratios.is_empty() && !rules.aspect_ratios.iter().any(matches) {
+            return Err(Violation::WrongAspectRatio { filename, width, height, allowed: rules.aspect_ratios.clone() });
+        }
+    }
+
+    Ok(())
+}
+
+fn attachment_allowed(rules: &PostRules, attachment: &AttachmentInfo) -> bool
+{
+    type_allowed(rules, attachment) && extension_allowed(rules, attachment)
+}
+
+fn type_allowed(rules: &PostRules, attachment: &AttachmentInfo) -> bool
+{
+    if rules.allowed_attachment_types.is_empty() { return true; }
+    let Some(content_type) = attachment.content_type else { return false };
+
+    rules.allowed_attachment_types.iter().any(|allowed| mime_matches(content_type, allowed))
+}
+
+fn extension_allowed(rules: &PostRules, attachment: &AttachmentInfo) -> bool
+{
+    if rules.allowed_extensions.is_empty() { return true; }
+    let Some((_, extension)) = attachment.filename.rsplit_once('.') else { return false };
+
+    rules.allowed_extensions.iter().any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(extension))
+}
+
+/// `allowed` is either a full MIME type or a `type/*` wildcard. Parameters like `; charset=` are ignored.
+fn mime_matches(content_type: &str, allowed: &str) -> bool
+{
+    let content_type = content_type.split(';').next().unwrap_or_default().trim();
+
+    match allowed.strip_suffix("/*") {
+        Some(top_level) => content_type.split('/').next().is_some_and(|top| top.eq_ignore_ascii_case(top_level)),
+        None => content_type.eq_ignore_ascii_case(allowed),
+    }
+}
+
+fn is_image(attachment: &AttachmentInfo) -> bool
+{
+    attachment.content_type.is_some_and(|content_type| mime_matches(content_type, "image/*"))
+}
+
+/// Every http(s) url in the text, with Discord's `<url>` embed suppression stripped.
+fn links(content: &str) -> impl Iterator<Item = &str>
+{
+    content.split_whitespace()
+        .map(|word| word.trim_start_matches('<').trim_end_matches('>'))
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+}
+
+/// `domain` matches its subdomains too, and may carry a path prefix.
+fn matches_domain(url: &str, domain: &str) -> bool
+{
+    let Some(rest) = url.split_once("://").map(|(_, rest)| rest) else { return false };
+    let (host, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let host = host.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().to_ascii_lowercase();
+
+    let (domain, prefix) = domain.split_at(domain.find('/').unwrap_or(domain.len()));
+    let domain = domain.to_ascii_lowercase();
+
+    (host == domain || host.ends_with(&format!(".{domain}"))) && path.starts_with(prefix)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn text(content: &str) -> Candidate<'_>
+    {
+        Candidate { content, ..Default::default() }
+    }
+
+    /// Discord fills in dimensions for images it could read.
+    fn attachment<'a>(filename: &'a str, content_type: &'a str) -> AttachmentInfo<'a>
+    {
+        let (width, height) = if content_type.starts_with("image/") { (Some(1920), Some(1080)) } else { (None, None) };
+        AttachmentInfo { filename, content_type: Some(content_type), width, height }
+    }
+
+    fn embed(url: &str) -> EmbedInfo<'_>
+    {
+        EmbedInfo { url: Some(url), has_image: true }
+    }
+
+    #[test]
+    fn defaults_skip_emoji_and_gifs()
+    {
+        let rules = PostRules::default();
+
+        assert_eq!(evaluate(&rules, &text("nice rice")), Err(Violation::NotAPost));
+        assert_eq!(evaluate(&rules, &text("https://i.imgur.com/a.png")), Ok(()));
+        assert_eq!(evaluate(&rules, &Candidate { attachments: vec![attachment("a.png", "image/png")], ..Default::default() }), Ok(()));
+
+        let gif = Candidate { content: "https://tenor.com/view/cat", embeds: vec![embed("https://tenor.com/view/cat")], ..Default::default() };
+        assert_eq!(evaluate(&rules, &gif), Err(Violation::NotAPost));
+
+        let emoji = Candidate { embeds: vec![embed("https://cdn.discordapp.com/emojis/1.png")], ..Default::default() };
+        assert_eq!(evaluate(&rules, &emoji), Err(Violation::NotAPost));
+
+        // other cdn paths are fine
+        let upload = Candidate { embeds: vec![embed("https://cdn.discordapp.com/attachments/1/2/a.png")], ..Default::default() };
+        assert_eq!(evaluate(&rules, &upload), Ok(()));
+    }
+
+    #[test]
+    fn defaults_are_the_original_check()
+    {
+        let rules = PostRules::default();
+
+        assert_eq!(evaluate(&rules, &text("look at this https://i.imgur.com/a.png")), Err(Violation::NotAPost));
+        assert_eq!(evaluate(&rules, &text("http://example.com")), Err(Violation::NotAPost));
+        assert_eq!(evaluate(&rules, &Candidate { content: "nice rice", in_thread: true, ..Default::default() }), Ok(()));
+
+        // a gif link counts until its embed shows up, as it always has
+        assert_eq!(evaluate(&rules, &text("https://tenor.com/view/cat")), Ok(()));
+
+        let rich = Candidate { embeds: vec![EmbedInfo { url: None, has_image: false }], ..Default::default() };
+        assert_eq!(evaluate(&rules, &rich), Ok(()));
+
+        let with_gif = Candidate {
+            attachments: vec![attachment("a.png", "image/png")],
+            embeds: vec![embed("https://tenor.com/view/cat")],
+            ..Default::default()
+        };
+        assert_eq!(evaluate(&rules, &with_gif), Err(Violation::NotAPost));
+    }
+
+    #[test]
+    fn loose_matching_takes_links_anywhere_and_attachments_with_gifs()
+    {
+        let rules = PostRules { loose_matching: true, ..Default::default() };
+
+        assert_eq!(evaluate(&rules, &text("my dotfiles: <https://example.com/dots>")), Ok(()));
+        assert_eq!(evaluate(&rules, &text("http://example.com")), Ok(()));
+        assert_eq!(evaluate(&rules, &text("https://tenor.com/view/cat")), Err(Violation::NotAPost));
+
+        let with_gif = Candidate {
+            attachments: vec![attachment("a.png", "image/png")],
+            embeds: vec![embed("https://tenor.com/view/cat")],
+            ..Default::default()
+        };
+        assert_eq!(evaluate(&rules, &with_gif), Ok(()));
+    }
+
+    #[test]
+    fn attachment_types_are_checked()
+    {
+        let rules = PostRules { allowed_attachment_types: vec!["image/*".to_owned(), "video/mp4".to_owned()], ..Default::default() };
+        let post = |attachments| Candidate { attachments, ..Default::default() };
+
+        assert_eq!(evaluate(&rules, &post(vec![attachment("a.PNG", "image/png")])), Ok(()));
+        assert_eq!(evaluate(&rules, &post(vec![attachment("a.mp4", "video/mp4")])), Ok(()));
+        assert_eq!(
+            evaluate(&rules, &post(vec![attachment("a.png", "image/png"), attachment("notes.txt", "text/plain; charset=utf-8")])),
+            Err(Violation::DisallowedAttachment { filename: "notes.txt".to_owned() })
+        );
+        assert_eq!(
+            evaluate(&rules, &post(vec![AttachmentInfo { filename: "mystery", content_type: None, width: None, height: None }])),
+            Err(Violation::DisallowedAttachment { filename: "mystery".to_owned() })
+        );
+    }
+
+    #[test]
+    fn extensions_are_checked_alongside_types()
+    {
+        let rules = PostRules {
+            allowed_attachment_types: vec!["application/pdf".to_owned(), "application/epub+zip".to_owned()],
+            allowed_extensions: vec!["pdf".to_owned(), ".epub".to_owned()],
+            ..Default::default()
+        };
+        let post = |attachment| Candidate { attachments: vec![attachment], ..Default::default() };
+
+        assert_eq!(evaluate(&rules, &post(attachment("sicp.PDF", "application/pdf"))), Ok(()));
+        assert_eq!(evaluate(&rules, &post(attachment("dune.epub", "application/epub+zip"))), Ok(()));
+        assert_eq!(
+            evaluate(&rules, &post(attachment("dune.zip", "application/epub+zip"))),
+            Err(Violation::DisallowedAttachment { filename: "dune.zip".to_owned() })
+        );
+        assert_eq!(
+            evaluate(&rules, &post(attachment("pdf", "application/pdf"))),
+            Err(Violation::DisallowedAttachment { filename: "pdf".to_owned() })
+        );
+    }
+
+    #[test]
+    fn images_need_dimensions_when_checked()
+    {
+        let rules = PostRules { allowed_attachment_types: vec!["image/*".to_owned()], check_image_dimensions: true, ..Default::default() };
+        let fake = AttachmentInfo { filename: "wall.png", content_type: Some("image/png"), width: None, height: None };
+
+        assert_eq!(evaluate(&rules, &Candidate { attachments: vec![attachment("wall.png", "image/png")], ..Default::default() }), Ok(()));
+        assert_eq!(
+            evaluate(&rules, &Candidate { attachments: vec![fake], ..Default::default() }),
+            Err(Violation::UnreadableImage { filename: "wall.png".to_owned() })
+        );
+    }
+
+    fn sized<'a>(filename: &'a str, width: u32, height: u32) -> AttachmentInfo<'a>
+    {
+        AttachmentInfo { filename, content_type: Some("image/png"), width: Some(width), height: Some(height) }
+    }
+
+    fn wallpaper_rules() -> ResolutionRules
+    {
+        ResolutionRules {
+            min_width: 1920,
+            min_height: 1080,
+            aspect_ratios: vec!["16:9".to_owned().try_into().unwrap(), "21:9".to_owned().try_into().unwrap()],
+            action: ResolutionAction::Warn,
+        }
+    }
+
+    #[test]
+    fn resolution_is_checked_in_either_orientation()
+    {
+        let rules = wallpaper_rules();
+
+        assert_eq!(check_resolution(&rules, &[sized("a.png", 3840, 2160)]), Ok(()));
+        assert_eq!(check_resolution(&rules, &[sized("phone.png", 1080, 1920)]), Ok(()));
+        assert_eq!(
+            check_resolution(&rules, &[sized("a.png", 2560, 1440), sized("small.png", 1280, 720)]),
+            Err(Violation::LowResolution { filename: "small.png".to_owned(), width: 1280, height: 720, min_width: 1920, min_height: 1080 })
+        );
+        // only images Discord could measure are checked
+        assert_eq!(check_resolution(&rules, &[attachment("a.txt", "text/plain")]), Ok(()));
+    }
+
+    #[test]
+    fn aspect_ratios_allow_a_little_slack()
+    {
+        let rules = wallpaper_rules();
+
+        assert_eq!(check_resolution(&rules, &[sized("ultrawide.png", 3440, 1440)]), Ok(()));
+        assert_eq!(check_resolution(&rules, &[sized("odd.png", 2732, 1536)]), Ok(()));
+        assert_eq!(
+            check_resolution(&rules, &[sized("a.png", 1920, 1200)]),
+            Err(Violation::WrongAspectRatio { filename: "a.png".to_owned(), width: 1920, height: 1200, allowed: rules.aspect_ratios.clone() })
+        );
+        assert_eq!(
+            check_resolution(&rules, &[sized("a.png", 1920, 1200)]).unwrap_err().to_string(),
+            "`a.png` is 1920x1200, this channel takes 16:9, 21:9 images"
+        );
+    }
+
+    #[test]
+    fn aspect_ratios_parse()
+    {
+        assert_eq!(AspectRatio::try_from("16:9".to_owned()), Ok(AspectRatio { width: 16, height: 9 }));
+        assert!(AspectRatio::try_from("16x9".to_owned()).is_err());
+        assert!(AspectRatio::try_from("0:9".to_owned()).is_err());
+    }
+
+    #[test]
+    fn required_domains_limit_which_links_count()
+    {
+        let rules = PostRules { required_domains: vec!["imgur.com".to_owned()], ..Default::default() };
+
+        assert_eq!(evaluate(&rules, &text("https://i.imgur.com/a.png")), Ok(()));
+        assert_eq!(evaluate(&rules, &text("https://imgur.com.evil.example/a.png")), Err(Violation::MissingRequiredDomain));
+        assert_eq!(evaluate(&rules, &text("https://example.com")), Err(Violation::MissingRequiredDomain));
+        assert_eq!(evaluate(&rules, &text("no links")), Err(Violation::NotAPost));
+    }
+
+    #[test]
+    fn github_showcase_needs_a_repo_link()
+    {
+        let rules = PostRules { require_github_link: true, ..Default::default() };
+
+        assert_eq!(evaluate(&rules, &text("https://github.com/me/edward")), Ok(()));
+        let screenshot = Candidate { attachments: vec![attachment("a.png", "image/png")], ..Default::default() };
+        assert_eq!(evaluate(&rules, &screenshot), Err(Violation::MissingGithubLink));
+        assert_eq!(evaluate(&rules, &text("https://gitlab.com/me/edward")), Err(Violation::MissingGithubLink));
+    }
+
+    #[test]
+    fn min_images_counts_attachments_and_embedded_images()
+    {
+        let rules = PostRules { min_images: 2, loose_matching: true, ..Default::default() };
+
+        let one = Candidate { attachments: vec![attachment("a.png", "image/png")], ..Default::default() };
+        assert_eq!(evaluate(&rules, &one), Err(Violation::TooFewImages { required: 2, found: 1 }));
+
+        let two = Candidate {
+            content: "https://i.imgur.com/b.png",
+            attachments: vec![attachment("a.png", "image/png")],
+            embeds: vec![embed("https://i.imgur.com/b.png")],
+            ..Default::default()
+        };
+        assert_eq!(evaluate(&rules, &two), Ok(()));
+
+        // forbidden embeds don't make up the numbers
+        let gif = Candidate { attachments: vec![attachment("a.png", "image/png")], embeds: vec![embed("https://tenor.com/x")], ..Default::default() };
+        assert_eq!(evaluate(&rules, &gif), Err(Violation::TooFewImages { required: 2, found: 1 }));
+    }
+
+    #[test]
+    fn thread_replies_are_let_through_unless_turned_off()
+    {
+        let reply = Candidate { content: "how did you get that bar?", in_thread: true, ..Default::default() };
+
+        assert_eq!(evaluate(&PostRules::default(), &reply), Ok(()));
+        assert_eq!(evaluate(&PostRules { allow_text_replies_in_threads: false, ..Default::default() }, &reply), Err(Violation::NotAPost));
+    }
+
+    #[test]
+    fn domains_match_hosts_not_substrings()
+    {
+        assert!(matches_domain("https://GitHub.com/a", "github.com"));
+        assert!(matches_domain("https://gist.github.com/a", "github.com"));
+        assert!(matches_domain("http://user@github.com:443/a", "github.com"));
+        assert!(!matches_domain("https://notgithub.com/a", "github.com"));
+        assert!(!matches_domain("https://cdn.discordapp.com/attachments/a", "cdn.discordapp.com/emojis"));
+    }
+}
@@ -0,0 +1,17 @@
+use std::{any::type_name, marker::PhantomData, sync::Arc};
+
+use serenity::prelude::{RwLock, TypeMap, TypeMapKey};
+use poise::serenity_prelude as serenity;
+
+/// Where each piece of Edward's shared state (the config, the store and so on) sits in the
+/// type map, one key per type, so systems can reach it through `Context::data`.
+pub struct Key<T>(PhantomData<T>);
+impl<T: Send + Sync + 'static> TypeMapKey for Key<T> { type Value = Arc<T>; }
+
+pub async fn get<T: Send + Sync + 'static>(data: &RwLock<TypeMap>) -> Arc<T>
+{
+    data.read().await
+        .get::<Key<T>>()
+        .cloned()
+        .unwrap_or_else(|| panic!("{} is inserted into the type map before the client starts", type_name::<T>()))
+}
@@ -0,0 +1,760 @@
+use std::{path::Path, sync::{Mutex, MutexGuard, PoisonError}};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serenity::model::{
+    channel::ReactionType,
+    id::{ChannelId, GuildId, MessageId, UserId},
+    Timestamp,
+};
+use poise::serenity_prelude as serenity;
+use anyhow::{anyhow, Context as _, Result};
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many have run,
+/// so existing entries must never be edited - append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE posts (
+        message_id  INTEGER PRIMARY KEY,
+        guild_id    INTEGER NOT NULL,
+        channel_id  INTEGER NOT NULL,
+        author_id   INTEGER NOT NULL,
+        content     TEXT    NOT NULL,
+        image_urls  TEXT    NOT NULL,
+        created_at  INTEGER NOT NULL
+    );
+    CREATE INDEX posts_by_channel ON posts (channel_id, created_at);
+
+    CREATE TABLE votes (
+        message_id  INTEGER NOT NULL REFERENCES posts (message_id) ON DELETE CASCADE,
+        emoji       TEXT    NOT NULL,
+        user_id     INTEGER NOT NULL,
+        PRIMARY KEY (message_id, emoji, user_id)
+    );
+
+    CREATE TABLE users (
+        user_id     INTEGER PRIMARY KEY,
+        name        TEXT    NOT NULL,
+        avatar_url  TEXT
+    );
+
+    CREATE TABLE moderation_actions (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id    INTEGER NOT NULL,
+        channel_id  INTEGER NOT NULL,
+        message_id  INTEGER,
+        user_id     INTEGER NOT NULL,
+        system      TEXT    NOT NULL,
+        rule        TEXT    NOT NULL,
+        action      TEXT    NOT NULL,
+        created_at  INTEGER NOT NULL
+    );
+    CREATE INDEX moderation_actions_by_user ON moderation_actions (guild_id, user_id);",
+
+    "CREATE TABLE backfills (
+        channel_id    INTEGER PRIMARY KEY,
+        completed_at  INTEGER NOT NULL
+    );",
+
+    "CREATE TABLE disabled_systems (
+        guild_id  INTEGER NOT NULL,
+        system    TEXT    NOT NULL,
+        PRIMARY KEY (guild_id, system)
+    );",
+
+    "CREATE TABLE dm_opt_outs (
+        user_id  INTEGER PRIMARY KEY
+    );",
+
+    "ALTER TABLE posts ADD COLUMN width INTEGER;
+    ALTER TABLE posts ADD COLUMN height INTEGER;",
+
+    // not tied to posts: hashes are taken before the post is indexed
+    "CREATE TABLE image_hashes (
+        message_id  INTEGER NOT NULL,
+        guild_id    INTEGER NOT NULL,
+        channel_id  INTEGER NOT NULL,
+        hash        INTEGER NOT NULL,
+        PRIMARY KEY (message_id, hash)
+    );
+    CREATE INDEX image_hashes_by_guild ON image_hashes (guild_id);",
+
+    // NULL: the whole history, which is what every earlier backfill walked
+    "ALTER TABLE backfills ADD COLUMN since INTEGER;",
+];
+
+/// Embedded SQLite database holding everything Edward needs to remember between events.
+pub struct Store
+{
+    conn: Mutex<Connection>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostRecord
+{
+    pub message_id: MessageId,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub author_id: UserId,
+    pub content: String,
+    pub image_urls: Vec<String>,
+
+    /// Width and height of the post's largest image, when Discord measured it.
+    pub resolution: Option<(u32, u32)>,
+    pub created_at: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserRecord
+{
+    pub user_id: UserId,
+    pub name: String,
+    pub avatar_url: Option<String>,
+}
+
+/// Perceptual hash of one image attachment, for repost detection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageHash
+{
+    pub message_id: MessageId,
+    pub guild_id: GuildId,
+
+    /// Where the message itself is, for linking to it: the thread, for forum posts.
+    pub channel_id: ChannelId,
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModerationAction
+{
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub message_id: Option<MessageId>,
+    pub user_id: UserId,
+    pub system: String,
+    pub rule: String,
+    pub action: String,
+    pub created_at: Timestamp,
+}
+
+impl Store
+{
+    pub fn open(path: impl AsRef<Path>) -> Result<Self>
+    {
+        let path = path.as_ref();
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed opening database `{}`", path.display()))?;
+
+        Self::from_connection(conn)
+            .with_context(|| format!("failed migrating database `{}`", path.display()))
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self>
+    {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+
+        let applied: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if applied > MIGRATIONS.len() {
+            return Err(anyhow!("database is at schema version {applied}, newer than this build ({})", MIGRATIONS.len()));
+        }
+
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)
+                .with_context(|| format!("migration #{} failed", version + 1))?;
+            tx.pragma_update(None, "user_version", version + 1)?;
+            tx.commit()?;
+        }
+
+        Ok(Store { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection>
+    {
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // posts
+
+    pub fn upsert_post(&self, post: &PostRecord) -> Result<()>
+    {
+        self.conn().execute(
+            "INSERT INTO posts (message_id, guild_id, channel_id, author_id, content, image_urls, width, height, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT (message_id) DO UPDATE SET
+                content = excluded.content,
+                image_urls = excluded.image_urls,
+                width = excluded.width,
+                height = excluded.height",
+            params![
+                sql_id(post.message_id.get()), sql_id(post.guild_id.get()), sql_id(post.channel_id.get()),
+                sql_id(post.author_id.get()), post.content, post.image_urls.join("\n"),
+                post.resolution.map(|(width, _)| width), post.resolution.map(|(_, height)| height),
+                post.created_at.unix_timestamp()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn post(&self, message_id: MessageId) -> Result<Option<PostRecord>>
+    {
+        Ok(self.conn()
+            .query_row("SELECT * FROM posts WHERE message_id = ?1", [sql_id(message_id.get())], post_from_row)
+            .optional()?)
+    }
+
+    /// Posts made from `since` on and before `until`, where given.
+    pub fn channel_posts(&self, channel_id: ChannelId, since: Option<Timestamp>, until: Option<Timestamp>) -> Result<Vec<PostRecord>>
+    {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT * FROM posts
+             WHERE channel_id = ?1 AND (?2 IS NULL OR created_at >= ?2) AND (?3 IS NULL OR created_at < ?3)
+             ORDER BY created_at DESC"
+        )?;
+        let posts = statement.query_map(
+            params![sql_id(channel_id.get()), since.map(|since| since.unix_timestamp()), until.map(|until| until.unix_timestamp())],
+            post_from_row,
+        )?;
+
+        Ok(posts.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Also drops the post's votes and image hashes.
+    pub fn delete_post(&self, message_id: MessageId) -> Result<bool>
+    {
+        let conn = self.conn();
+        conn.execute("DELETE FROM image_hashes WHERE message_id = ?1", [sql_id(message_id.get())])?;
+
+        Ok(conn.execute("DELETE FROM posts WHERE message_id = ?1", [sql_id(message_id.get())])? > 0)
+    }
+
+    // image hashes
+
+    pub fn add_image_hash(&self, image: &ImageHash) -> Result<()>
+    {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO image_hashes (message_id, guild_id, channel_id, hash) VALUES (?1, ?2, ?3, ?4)",
+            params![sql_id(image.message_id.get()), sql_id(image.guild_id.get()), sql_id(image.channel_id.get()), image.hash as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// Oldest first. SQLite can't count differing bits, so matching is left to the caller.
+    pub fn guild_image_hashes(&self, guild_id: GuildId) -> Result<Vec<ImageHash>>
+    {
+        let conn = self.conn();
+        let mut statement = conn.prepare("SELECT * FROM image_hashes WHERE guild_id = ?1 ORDER BY message_id")?;
+        let images = statement.query_map(
+            [sql_id(guild_id.get())],
+            |row| Ok(ImageHash {
+                message_id: id_column(row, "message_id")?,
+                guild_id: id_column(row, "guild_id")?,
+                channel_id: id_column(row, "channel_id")?,
+                hash: row.get::<_, i64>("hash")? as u64,
+            }),
+        )?;
+
+        Ok(images.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // votes
+
+    /// Returns false when the vote was already recorded or the message isn't a known post.
+    pub fn add_vote(&self, message_id: MessageId, emoji: &str, user_id: UserId) -> Result<bool>
+    {
+        Ok(self.conn().execute(
+            "INSERT OR IGNORE INTO votes (message_id, emoji, user_id)
+             SELECT ?1, ?2, ?3 WHERE EXISTS (SELECT 1 FROM posts WHERE message_id = ?1)",
+            params![sql_id(message_id.get()), emoji, sql_id(user_id.get())],
+        )? > 0)
+    }
+
+    pub fn remove_vote(&self, message_id: MessageId, emoji: &str, user_id: UserId) -> Result<bool>
+    {
+        Ok(self.conn().execute(
+            "DELETE FROM votes WHERE message_id = ?1 AND emoji = ?2 AND user_id = ?3",
+            params![sql_id(message_id.get()), emoji, sql_id(user_id.get())],
+        )? > 0)
+    }
+
+    /// `emoji = None` clears every reaction on the message. `keep`'s votes survive the purge.
+    pub fn clear_votes(&self, message_id: MessageId, emoji: Option<&str>, keep: Option<UserId>) -> Result<usize>
+    {
+        Ok(self.conn().execute(
+            "DELETE FROM votes WHERE message_id = ?1 AND (?2 IS NULL OR emoji = ?2) AND user_id IS NOT ?3",
+            params![sql_id(message_id.get()), emoji, keep.map(|id| sql_id(id.get()))],
+        )?)
+    }
+
+    /// Per-emoji vote counts for every indexed post in the channel. Authors voting on their own
+    /// posts don't count, and neither does `exclude` (Edward, whose seeded votes are indexed too).
+    pub fn channel_vote_counts(&self, channel_id: ChannelId, exclude: UserId) -> Result<Vec<(MessageId, String, u64)>>
+    {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT votes.message_id, votes.emoji, COUNT(*) FROM votes
+             JOIN posts ON posts.message_id = votes.message_id
+             WHERE posts.channel_id = ?1 AND votes.user_id != posts.author_id AND votes.user_id != ?2
+             GROUP BY votes.message_id, votes.emoji"
+        )?;
+        let counts = statement.query_map(
+            params![sql_id(channel_id.get()), sql_id(exclude.get())],
+            |row| Ok((id_column(row, "message_id")?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        Ok(counts.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Replaces every recorded voter of `emoji` on the message in one transaction.
+    pub fn replace_votes(&self, message_id: MessageId, emoji: &str, user_ids: &[UserId]) -> Result<()>
+    {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "DELETE FROM votes WHERE message_id = ?1 AND emoji = ?2",
+            params![sql_id(message_id.get()), emoji],
+        )?;
+
+        for user_id in user_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO votes (message_id, emoji, user_id) VALUES (?1, ?2, ?3)",
+                params![sql_id(message_id.get()), emoji, sql_id(user_id.get())],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // backfills
+
+    /// Whether a backfill has covered the channel back to `since`, or to its start for `None`.
+    pub fn is_backfilled(&self, channel_id: ChannelId, since: Option<Timestamp>) -> Result<bool>
+    {
+        Ok(self.conn()
+            .query_row(
+                "SELECT 1 FROM backfills WHERE channel_id = ?1 AND (since IS NULL OR since <= ?2)",
+                params![sql_id(channel_id.get()), since.map(|since| since.unix_timestamp())],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Only ever widens: callers backfill when `is_backfilled` says `since` isn't covered yet.
+    pub fn mark_backfilled(&self, channel_id: ChannelId, completed_at: Timestamp, since: Option<Timestamp>) -> Result<()>
+    {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO backfills (channel_id, completed_at, since) VALUES (?1, ?2, ?3)",
+            params![sql_id(channel_id.get()), completed_at.unix_timestamp(), since.map(|since| since.unix_timestamp())],
+        )?;
+
+        Ok(())
+    }
+
+    // system toggles
+
+    pub fn disabled_systems(&self) -> Result<Vec<(GuildId, String)>>
+    {
+        let conn = self.conn();
+        let mut statement = conn.prepare("SELECT guild_id, system FROM disabled_systems")?;
+        let disabled = statement.query_map([], |row| Ok((id_column(row, "guild_id")?, row.get("system")?)))?;
+
+        Ok(disabled.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Returns whether the system's state actually changed.
+    pub fn set_system_enabled(&self, guild_id: GuildId, system: &str, enabled: bool) -> Result<bool>
+    {
+        let sql = if enabled { "DELETE FROM disabled_systems WHERE guild_id = ?1 AND system = ?2" }
+                  else { "INSERT OR IGNORE INTO disabled_systems (guild_id, system) VALUES (?1, ?2)" };
+
+        Ok(self.conn().execute(sql, params![sql_id(guild_id.get()), system])? > 0)
+    }
+
+    // dm opt-outs
+
+    pub fn is_dm_opted_out(&self, user_id: UserId) -> Result<bool>
+    {
+        Ok(self.conn()
+            .query_row("SELECT 1 FROM dm_opt_outs WHERE user_id = ?1", [sql_id(user_id.get())], |_| Ok(()))
+            .optional()?
+            .is_some())
+    }
+
+    pub fn set_dm_opt_out(&self, user_id: UserId, opted_out: bool) -> Result<()>
+    {
+        let sql = if opted_out { "INSERT OR IGNORE INTO dm_opt_outs (user_id) VALUES (?1)" }
+                  else { "DELETE FROM dm_opt_outs WHERE user_id = ?1" };
+
+        self.conn().execute(sql, [sql_id(user_id.get())])?;
+        Ok(())
+    }
+
+    // users
+
+    pub fn upsert_user(&self, user: &UserRecord) -> Result<()>
+    {
+        self.conn().execute(
+            "INSERT INTO users (user_id, name, avatar_url) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id) DO UPDATE SET name = excluded.name, avatar_url = excluded.avatar_url",
+            params![sql_id(user.user_id.get()), user.name, user.avatar_url],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn user(&self, user_id: UserId) -> Result<Option<UserRecord>>
+    {
+        Ok(self.conn()
+            .query_row(
+                "SELECT user_id, name, avatar_url FROM users WHERE user_id = ?1",
+                [sql_id(user_id.get())],
+                |row| Ok(UserRecord { user_id: id_column(row, "user_id")?, name: row.get("name")?, avatar_url: row.get("avatar_url")? }),
+            )
+            .optional()?)
+    }
+
+    // moderation actions
+
+    pub fn record_moderation_action(&self, action: &ModerationAction) -> Result<i64>
+    {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO moderation_actions (guild_id, channel_id, message_id, user_id, system, rule, action, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                sql_id(action.guild_id.get()), sql_id(action.channel_id.get()),
+                action.message_id.map(|id| sql_id(id.get())), sql_id(action.user_id.get()),
+                action.system, action.rule, action.action, action.created_at.unix_timestamp()
+            ],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+}
+
+/// Votes are keyed by custom emoji id, or by the emoji itself for unicode reactions.
+pub fn emoji_key(reaction: &ReactionType) -> String
+{
+    match reaction {
+        ReactionType::Custom { id, .. } => id.to_string(),
+        ReactionType::Unicode(emoji) => emoji.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Snowflakes fit in 63 bits, so they round-trip through SQLite's signed integers.
+fn sql_id(id: u64) -> i64 { id as i64 }
+
+fn id_column<Id: From<u64>>(row: &Row, column: &str) -> rusqlite::Result<Id>
+{
+    row.get::<_, i64>(column).map(|id| Id::from(id as u64))
+}
+
+fn timestamp_column(row: &Row, column: &str) -> rusqlite::Result<Timestamp>
+{
+    let secs: i64 = row.get(column)?;
+    Timestamp::from_unix_timestamp(secs)
+        .map_err(|why| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Integer, Box::new(why)))
+}
+
+fn post_from_row(row: &Row) -> rusqlite::Result<PostRecord>
+{
+    let image_urls: String = row.get("image_urls")?;
+    let (width, height): (Option<u32>, Option<u32>) = (row.get("width")?, row.get("height")?);
+
+    Ok(PostRecord {
+        message_id: id_column(row, "message_id")?,
+        guild_id: id_column(row, "guild_id")?,
+        channel_id: id_column(row, "channel_id")?,
+        author_id: id_column(row, "author_id")?,
+        content: row.get("content")?,
+        image_urls: image_urls.lines().map(str::to_owned).collect(),
+        resolution: width.zip(height),
+        created_at: timestamp_column(row, "created_at")?,
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Reads only the tests need: `/fetch` goes through `channel_vote_counts`, and moderation
+    /// actions are only ever written.
+    impl Store
+    {
+        fn vote_counts(&self, message_id: MessageId) -> Result<Vec<(String, u64)>>
+        {
+            let conn = self.conn();
+            let mut statement = conn.prepare(
+                "SELECT emoji, COUNT(*) FROM votes WHERE message_id = ?1 GROUP BY emoji ORDER BY emoji"
+            )?;
+            let counts = statement.query_map([sql_id(message_id.get())], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+            Ok(counts.collect::<rusqlite::Result<_>>()?)
+        }
+
+        fn voters(&self, message_id: MessageId, emoji: &str) -> Result<Vec<UserId>>
+        {
+            let conn = self.conn();
+            let mut statement = conn.prepare("SELECT user_id FROM votes WHERE message_id = ?1 AND emoji = ?2")?;
+            let users = statement.query_map(params![sql_id(message_id.get()), emoji], |row| id_column(row, "user_id"))?;
+
+            Ok(users.collect::<rusqlite::Result<_>>()?)
+        }
+
+        /// Most recent first.
+        fn moderation_actions_for_user(&self, guild_id: GuildId, user_id: UserId, limit: usize) -> Result<Vec<ModerationAction>>
+        {
+            let conn = self.conn();
+            let mut statement = conn.prepare(
+                "SELECT * FROM moderation_actions WHERE guild_id = ?1 AND user_id = ?2 ORDER BY id DESC LIMIT ?3"
+            )?;
+            let actions = statement.query_map(
+                params![sql_id(guild_id.get()), sql_id(user_id.get()), limit as i64],
+                |row| Ok(ModerationAction {
+                    guild_id: id_column(row, "guild_id")?,
+                    channel_id: id_column(row, "channel_id")?,
+                    message_id: row.get::<_, Option<i64>>("message_id")?.map(|id| MessageId::new(id as u64)),
+                    user_id: id_column(row, "user_id")?,
+                    system: row.get("system")?,
+                    rule: row.get("rule")?,
+                    action: row.get("action")?,
+                    created_at: timestamp_column(row, "created_at")?,
+                }),
+            )?;
+
+            Ok(actions.collect::<rusqlite::Result<_>>()?)
+        }
+    }
+
+    fn temp_store() -> (tempfile::TempDir, Store)
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(dir.path().join("edward.db")).unwrap();
+        (dir, store)
+    }
+
+    fn post(message_id: u64, channel_id: u64) -> PostRecord
+    {
+        PostRecord {
+            message_id: MessageId::new(message_id),
+            guild_id: GuildId::new(1),
+            channel_id: ChannelId::new(channel_id),
+            author_id: UserId::new(7),
+            content: "look at my rice".to_owned(),
+            image_urls: vec!["https://example.com/a.png".to_owned(), "https://example.com/b.png".to_owned()],
+            resolution: Some((1920, 1080)),
+            created_at: Timestamp::from_unix_timestamp(1_700_000_000 + message_id as i64).unwrap(),
+        }
+    }
+
+    #[test]
+    fn reopening_keeps_data_and_skips_applied_migrations()
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("edward.db");
+
+        Store::open(&path).unwrap().upsert_post(&post(10, 2)).unwrap();
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(store.post(MessageId::new(10)).unwrap(), Some(post(10, 2)));
+    }
+
+    #[test]
+    fn posts_round_trip_and_list_newest_first()
+    {
+        let (_dir, store) = temp_store();
+        store.upsert_post(&post(10, 2)).unwrap();
+        store.upsert_post(&post(11, 2)).unwrap();
+        store.upsert_post(&post(12, 3)).unwrap();
+
+        let mut edited = post(10, 2);
+        edited.content = "edited".to_owned();
+        store.upsert_post(&edited).unwrap();
+
+        let posts = store.channel_posts(ChannelId::new(2), None, None).unwrap();
+        assert_eq!(posts, vec![post(11, 2), edited]);
+    }
+
+    #[test]
+    fn channel_posts_include_since_and_exclude_until()
+    {
+        let (_dir, store) = temp_store();
+        for id in 10..14 { store.upsert_post(&post(id, 2)).unwrap(); }
+
+        let (since, until) = (post(11, 2).created_at, post(13, 2).created_at);
+        let ids = |posts: Vec<PostRecord>| posts.iter().map(|post| post.message_id.get()).collect::<Vec<_>>();
+
+        assert_eq!(ids(store.channel_posts(ChannelId::new(2), Some(since), Some(until)).unwrap()), [12, 11]);
+        assert_eq!(ids(store.channel_posts(ChannelId::new(2), Some(since), None).unwrap()), [13, 12, 11]);
+        assert_eq!(ids(store.channel_posts(ChannelId::new(2), None, Some(until)).unwrap()), [12, 11, 10]);
+    }
+
+    #[test]
+    fn votes_only_attach_to_known_posts_and_cascade_on_delete()
+    {
+        let (_dir, store) = temp_store();
+        let (message, user) = (MessageId::new(10), UserId::new(5));
+
+        assert!(!store.add_vote(message, "💙", user).unwrap());
+
+        store.upsert_post(&post(10, 2)).unwrap();
+        assert!(store.add_vote(message, "💙", user).unwrap());
+        assert!(!store.add_vote(message, "💙", user).unwrap());
+        assert!(store.add_vote(message, "💙", UserId::new(6)).unwrap());
+        assert!(store.add_vote(message, "1343553189508681728", user).unwrap());
+
+        assert_eq!(store.vote_counts(message).unwrap(), vec![("1343553189508681728".to_owned(), 1), ("💙".to_owned(), 2)]);
+
+        assert_eq!(store.channel_vote_counts(ChannelId::new(2), UserId::new(1)).unwrap().len(), 2);
+
+        // the author's own vote and the excluded user's are recorded, not counted
+        assert!(store.add_vote(message, "💙", UserId::new(7)).unwrap());
+        assert!(store.add_vote(message, "💙", UserId::new(1)).unwrap());
+        assert_eq!(
+            store.channel_vote_counts(ChannelId::new(2), UserId::new(1)).unwrap(),
+            vec![(message, "1343553189508681728".to_owned(), 1), (message, "💙".to_owned(), 2)]
+        );
+        store.remove_vote(message, "💙", UserId::new(7)).unwrap();
+        store.remove_vote(message, "💙", UserId::new(1)).unwrap();
+
+        assert!(store.remove_vote(message, "💙", user).unwrap());
+        assert_eq!(store.voters(message, "💙").unwrap(), vec![UserId::new(6)]);
+
+        assert_eq!(store.clear_votes(message, None, Some(user)).unwrap(), 1);
+        assert_eq!(store.vote_counts(message).unwrap(), vec![("1343553189508681728".to_owned(), 1)]);
+        assert!(store.delete_post(message).unwrap());
+        assert!(store.vote_counts(message).unwrap().is_empty());
+    }
+
+    #[test]
+    fn replace_votes_overwrites_one_emoji()
+    {
+        let (_dir, store) = temp_store();
+        let message = MessageId::new(10);
+        store.upsert_post(&post(10, 2)).unwrap();
+        store.add_vote(message, "💙", UserId::new(5)).unwrap();
+        store.add_vote(message, "😂", UserId::new(5)).unwrap();
+
+        store.replace_votes(message, "💙", &[UserId::new(6), UserId::new(7)]).unwrap();
+
+        assert_eq!(store.vote_counts(message).unwrap(), vec![("💙".to_owned(), 2), ("😂".to_owned(), 1)]);
+    }
+
+    #[test]
+    fn image_hashes_are_listed_per_guild_and_dropped_with_their_post()
+    {
+        let (_dir, store) = temp_store();
+        let image = |message_id, guild_id, hash| ImageHash {
+            message_id: MessageId::new(message_id),
+            guild_id: GuildId::new(guild_id),
+            channel_id: ChannelId::new(2),
+            hash,
+        };
+
+        store.add_image_hash(&image(11, 1, u64::MAX)).unwrap();
+        store.add_image_hash(&image(10, 1, 1 << 63)).unwrap();
+        store.add_image_hash(&image(10, 1, 1 << 63)).unwrap();
+        store.add_image_hash(&image(12, 5, 0)).unwrap();
+
+        assert_eq!(store.guild_image_hashes(GuildId::new(1)).unwrap(), vec![image(10, 1, 1 << 63), image(11, 1, u64::MAX)]);
+
+        // hashes outlive a post that was never indexed, but not its deletion
+        store.delete_post(MessageId::new(10)).unwrap();
+        assert_eq!(store.guild_image_hashes(GuildId::new(1)).unwrap(), vec![image(11, 1, u64::MAX)]);
+    }
+
+    #[test]
+    fn backfills_are_tracked_per_channel()
+    {
+        let (_dir, store) = temp_store();
+        let (older, newer) = (post(10, 2).created_at, post(11, 2).created_at);
+        assert!(!store.is_backfilled(ChannelId::new(2), None).unwrap());
+
+        store.mark_backfilled(ChannelId::new(2), Timestamp::now(), Some(newer)).unwrap();
+        assert!(store.is_backfilled(ChannelId::new(2), Some(newer)).unwrap());
+        assert!(!store.is_backfilled(ChannelId::new(2), Some(older)).unwrap());
+        assert!(!store.is_backfilled(ChannelId::new(2), None).unwrap());
+
+        store.mark_backfilled(ChannelId::new(2), Timestamp::now(), None).unwrap();
+        assert!(store.is_backfilled(ChannelId::new(2), Some(older)).unwrap());
+        assert!(store.is_backfilled(ChannelId::new(2), None).unwrap());
+        assert!(!store.is_backfilled(ChannelId::new(3), None).unwrap());
+    }
+
+    #[test]
+    fn systems_are_disabled_per_guild()
+    {
+        let (_dir, store) = temp_store();
+        assert!(store.set_system_enabled(GuildId::new(1), "rizz_ping", false).unwrap());
+        assert!(!store.set_system_enabled(GuildId::new(1), "rizz_ping", false).unwrap());
+        assert!(store.set_system_enabled(GuildId::new(2), "index_post", false).unwrap());
+
+        let mut disabled = store.disabled_systems().unwrap();
+        disabled.sort();
+        assert_eq!(disabled, vec![(GuildId::new(1), "rizz_ping".to_owned()), (GuildId::new(2), "index_post".to_owned())]);
+
+        assert!(store.set_system_enabled(GuildId::new(1), "rizz_ping", true).unwrap());
+        assert!(!store.set_system_enabled(GuildId::new(1), "rizz_ping", true).unwrap());
+        assert_eq!(store.disabled_systems().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn dm_opt_outs_toggle()
+    {
+        let (_dir, store) = temp_store();
+        assert!(!store.is_dm_opted_out(UserId::new(5)).unwrap());
+
+        store.set_dm_opt_out(UserId::new(5), true).unwrap();
+        store.set_dm_opt_out(UserId::new(5), true).unwrap();
+        assert!(store.is_dm_opted_out(UserId::new(5)).unwrap());
+        assert!(!store.is_dm_opted_out(UserId::new(6)).unwrap());
+
+        store.set_dm_opt_out(UserId::new(5), false).unwrap();
+        assert!(!store.is_dm_opted_out(UserId::new(5)).unwrap());
+    }
+
+    #[test]
+    fn users_upsert()
+    {
+        let (_dir, store) = temp_store();
+        let mut user = UserRecord { user_id: UserId::new(5), name: "cisco".to_owned(), avatar_url: None };
+        store.upsert_user(&user).unwrap();
+
+        user.avatar_url = Some("https://example.com/avatar.png".to_owned());
+        store.upsert_user(&user).unwrap();
+
+        assert_eq!(store.user(UserId::new(5)).unwrap(), Some(user));
+        assert_eq!(store.user(UserId::new(6)).unwrap(), None);
+    }
+
+    #[test]
+    fn moderation_actions_list_most_recent_first()
+    {
+        let (_dir, store) = temp_store();
+        let action = |rule: &str| ModerationAction {
+            guild_id: GuildId::new(1),
+            channel_id: ChannelId::new(2),
+            message_id: Some(MessageId::new(10)),
+            user_id: UserId::new(5),
+            system: "showcase_cleaner_and_voter".to_owned(),
+            rule: rule.to_owned(),
+            action: "delete".to_owned(),
+            created_at: Timestamp::from_unix_timestamp(1_700_000_000).unwrap(),
+        };
+
+        let first = store.record_moderation_action(&action("not_a_post")).unwrap();
+        let second = store.record_moderation_action(&action("emoji_only")).unwrap();
+        assert!(second > first);
+
+        let actions = store.moderation_actions_for_user(GuildId::new(1), UserId::new(5), 10).unwrap();
+        assert_eq!(actions, vec![action("emoji_only"), action("not_a_post")]);
+    }
+}
@@ -1,84 +1,314 @@
 use std::future::Future;
 use serenity::{
-    model::{channel::{Message, Reaction}, id::EmojiId},
+    model::{channel::{Message, Reaction}, id::{ChannelId, GuildId, MessageId}},
     all::ReactionType,
-    prelude::*,
 };
 use poise::serenity_prelude as serenity;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
 
-use crate::{group_system, SHOWCASE_CHANNELS, VOTE_CHANNELS, BLACKLISTED_REACTION_USERS};
-use group_system::Propagation;
+use crate::{config, group_system, notices, rules::{self, Candidate, ResolutionAction, Violation}, shared, store::{self, emoji_key}};
+use config::GuildConfig;
+use group_system::{Action, ModCtx, Propagation, ReactionRemoveAll, ReactionRemoveEmoji, ReadOnlyCtx, ReplyCtx, Verdict};
 
 /// DynamicProcessor
-pub async fn rizz_ping(ctx: &mut Context, msg: &Message)
+pub async fn rizz_ping(ctx: &ReplyCtx, msg: &Message) -> Result<()>
 {
     if msg.content.to_lowercase().contains("!rizz") {
-        if let Err(why) = msg.channel_id.say(&ctx.http, "\\*looksmaxxes\\*").await {
-            println!("Error sending message: {why:?}");
+        ctx.say(msg.channel_id, "\\*looksmaxxes\\*").await?;
+    }
+
+    Ok(())
+}
+
+/// ModerationProcessor
+pub async fn showcase_cleaner_and_voter(ctx: &ModCtx, msg: &Message) -> Result<Propagation>
+{
+    if is_own_message(ctx, msg) { return Ok(Propagation::Propagate); }
+
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = msg.guild_id.and_then(|id| config.guild(id)) else { return Ok(Propagation::Propagate) };
+
+    let parent = ctx.thread_parent(guild.id, msg.channel_id).await
+        .with_context(|| format!("looking up the parent of channel {}", msg.channel_id))?;
+
+    let channel_id = match placement(guild, msg, parent) {
+        Some(Placement::Post(channel_id)) => channel_id,
+
+        // thread messages are held to their channel's rules, but never voted on
+        Some(Placement::Reply(channel_id)) if guild.is_showcase_channel(channel_id) => {
+            let candidate = Candidate::from_message(msg, true);
+
+            return match rules::evaluate(&guild.post_rules(channel_id), &candidate) {
+                Ok(()) => Ok(Propagation::Propagate),
+                Err(violation) => remove_message(ctx, msg, channel_id, guild, violation).await,
+            };
         }
+
+        Some(Placement::Reply(_)) | None => return Ok(Propagation::Propagate),
+    };
+
+    match check_post(msg, guild, channel_id) {
+        Ok(()) => add_vote_reactions(ctx, msg, guild).await?,
+        Err(_) if guild.is_vote_channel(channel_id) => remove_vote_reactions(ctx, msg, guild).await?,
+        Err(violation) => return remove_message(ctx, msg, channel_id, guild, violation).await,
+    }
+
+    Ok(Propagation::Propagate)
+}
+
+/// Edward's own replies, like resolution warnings and repost links, are never posts,
+/// whatever they link to.
+pub fn is_own_message(ctx: &ReadOnlyCtx, msg: &Message) -> bool
+{
+    msg.author.id == ctx.cache().current_user().id
+}
+
+/// Where a message sits relative to the guild's showcase/vote channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Placement
+{
+    /// Straight in the channel, or opening one of its forum threads.
+    Post(ChannelId),
+
+    /// Any other message in one of the channel's threads.
+    Reply(ChannelId),
+}
+
+/// The showcase/vote channel the message is a post in: the channel itself, or the forum for the
+/// opening message of a forum thread. `None` for replies and anything outside those channels.
+pub async fn post_channel(ctx: &ReplyCtx, guild: &GuildConfig, msg: &Message) -> Result<Option<ChannelId>>
+{
+    let parent = ctx.thread_parent(guild.id, msg.channel_id).await
+        .with_context(|| format!("looking up the parent of channel {}", msg.channel_id))?;
+
+    Ok(match placement(guild, msg, parent) {
+        Some(Placement::Post(channel_id)) => Some(channel_id),
+        Some(Placement::Reply(_)) | None => None,
+    })
+}
+
+/// `parent` is the message's channel's parent if that channel is a thread. `None` for messages
+/// outside showcase/vote channels and their threads.
+pub fn placement(guild: &GuildConfig, msg: &Message, parent: Option<ChannelId>) -> Option<Placement>
+{
+    match parent {
+        Some(parent) if !guild.is_watched_channel(parent) => None,
+        Some(parent) if is_forum_starter(msg) => Some(Placement::Post(parent)),
+        Some(parent) => Some(Placement::Reply(parent)),
+        None => guild.is_watched_channel(msg.channel_id).then_some(Placement::Post(msg.channel_id)),
     }
 }
 
+/// Forum threads share their id with their opening message. Threads opened on a message in a
+/// text channel do too, but that message lives in the parent channel.
+fn is_forum_starter(msg: &Message) -> bool
+{
+    msg.id.get() == msg.channel_id.get()
+}
+
 /// ModerationProcessor
-pub async fn showcase_cleaner_and_voter(ctx: &mut Context, msg: &Message) -> Propagation
-{
-    if SHOWCASE_CHANNELS.contains(&msg.channel_id.get()) || VOTE_CHANNELS.contains(&msg.channel_id.get()) {
-        let is_post = msg.attachments.len() > 0
-            || msg.embeds.len() > 0
-            || msg.content.starts_with("https://");
-
-        let is_post = is_post && !(
-            !msg.embeds.is_empty() &&
-            msg.embeds.iter().all(|embed| {
-                embed.url
-                    .as_deref()
-                    .unwrap_or_default()
-                    .starts_with("https://cdn.discordapp.com/emojis") ||
-                embed.url
-                    .as_deref()
-                    .unwrap_or_default()
-                    .contains("tenor.com")
-            })
-        );
-
-        if is_post { add_vote_reactions(&ctx, &msg).await; }
-        else if !VOTE_CHANNELS.contains(&msg.channel_id.get()) {
-            while let Err(why) = msg.delete(&ctx.http).await {
-                eprintln!("Error deleting message by {}: {why:?}", msg.author.name);
-            }
+pub async fn enforce_wallpaper_resolution(ctx: &ModCtx, msg: &Message) -> Result<Propagation>
+{
+    check_resolution(ctx, msg, true).await
+}
 
-            return Propagation::Stop;
+/// ModerationProcessor
+///
+/// Edits get removed like new posts, but aren't warned about again: the post was when it went up,
+/// and every later update, link unfurls included, would repeat it.
+pub async fn enforce_edited_wallpaper_resolution(ctx: &ModCtx, msg: &Message) -> Result<Propagation>
+{
+    check_resolution(ctx, msg, false).await
+}
+
+async fn check_resolution(ctx: &ModCtx, msg: &Message, warn: bool) -> Result<Propagation>
+{
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = msg.guild_id.and_then(|id| config.guild(id)) else { return Ok(Propagation::Propagate) };
+    if guild.resolution_rules.is_empty() { return Ok(Propagation::Propagate); }
+
+    let Some(channel_id) = post_channel(ctx, guild, msg).await? else { return Ok(Propagation::Propagate) };
+    let Some(rules) = guild.resolution_rules.get(&channel_id) else { return Ok(Propagation::Propagate) };
+
+    let candidate = Candidate::from_message(msg, false);
+    let Err(violation) = rules::check_resolution(rules, &candidate.attachments) else { return Ok(Propagation::Propagate) };
+
+    match rules.action {
+        ResolutionAction::Remove => remove_message(ctx, msg, channel_id, guild, violation).await,
+        ResolutionAction::Warn if !warn => Ok(Propagation::Propagate),
+        ResolutionAction::Warn => {
+            ctx.reply(msg, format!("Heads up: {violation}.")).await
+                .with_context(|| format!("warning {} about post {}", msg.author.name, msg.id))?;
+            Ok(Propagation::Propagate)
         }
     }
+}
 
-    Propagation::Propagate
+/// Deletes a message that broke the rules of `channel_id`, its showcase/vote channel, and tells its author why.
+pub async fn remove_message(ctx: &ModCtx, msg: &Message, channel_id: ChannelId, guild: &GuildConfig, violation: Violation) -> Result<Propagation>
+{
+    // a forum thread without its opening post is an empty husk
+    if is_forum_starter(msg) {
+        retry(3, msg.channel_id, async |id| ctx.delete_thread(id).await).await
+            .with_context(|| format!("deleting forum thread {} by {}", msg.channel_id, msg.author.name))?;
+    } else {
+        retry(3, msg.id, async |id| ctx.delete_message(msg.channel_id, id).await).await
+            .with_context(|| format!("deleting message {} by {}", msg.id, msg.author.name))?;
+    }
+
+    // a closed DM channel is no reason to fail the deletion
+    if let Err(why) = notices::notify_removed_message(ctx, msg, channel_id, guild, &violation).await {
+        eprintln!("Error notifying {} about their removed message: {why:?}", msg.author.name);
+    }
+
+    Ok(Propagation::Stop(Verdict { rule: violation.rule(), action: Action::DeleteMessage }))
 }
 
 /// ModerationProcessor
-pub async fn block_blacklisted_reactors(ctx: &mut Context, reaction: &Reaction) -> Propagation
+pub async fn block_blacklisted_reactors(ctx: &ModCtx, reaction: &Reaction) -> Result<Propagation>
+{
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = reaction.guild_id.and_then(|id| config.guild(id)) else { return Ok(Propagation::Propagate) };
+    let Some(user_id) = reaction.user_id else { return Ok(Propagation::Propagate) };
+
+    if guild.blacklisted_reaction_users.contains(&user_id) {
+        ctx.delete_reaction(reaction).await
+            .with_context(|| format!("removing blacklisted user {user_id}'s reaction on {}", reaction.message_id))?;
+        return Ok(Propagation::Stop(Verdict { rule: "blacklisted_reactor", action: Action::RemoveReaction }));
+    }
+
+    Ok(Propagation::Propagate)
+}
+
+/// Whether a message in a showcase/vote channel counts as a post (and gets voted on),
+/// going by the channel's post rules. Forum posts are checked against the forum's.
+pub fn check_post(msg: &Message, guild: &GuildConfig, channel_id: ChannelId) -> Result<(), Violation>
+{
+    rules::evaluate(&guild.post_rules(channel_id), &Candidate::from_message(msg, false))
+}
+
+/// DynamicProcessor
+pub async fn open_discussion_thread(ctx: &ReplyCtx, msg: &Message) -> Result<()>
 {
-    let user_id = reaction.user_id.expect("FAILED_RETRIEVING_REACTION_USER").get();
+    if is_own_message(ctx, msg) { return Ok(()); }
 
-    if BLACKLISTED_REACTION_USERS.contains(&user_id) {
-        reaction.delete(&ctx.http).await.expect("FAILED_REMOVING_BLACKLISTED_USER_REACTION");
-        return Propagation::Stop;
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = msg.guild_id.and_then(|id| config.guild(id)) else { return Ok(()) };
+    if guild.channels.discussion_threads.is_empty() || msg.thread.is_some() { return Ok(()); }
+
+    let Some(channel_id) = post_channel(ctx, guild, msg).await? else { return Ok(()) };
+    if !guild.channels.discussion_threads.contains(&channel_id) { return Ok(()); }
+
+    // a forum post is its own discussion thread
+    if is_forum_starter(msg) { return Ok(()); }
+    if check_post(msg, guild, channel_id).is_err() { return Ok(()); }
+
+    ctx.create_thread(msg, thread_name(msg)).await
+        .with_context(|| format!("opening a discussion thread on post {}", msg.id))?;
+
+    Ok(())
+}
+
+/// The post's first line of text, or its author's name for posts that are only links and files.
+fn thread_name(msg: &Message) -> String
+{
+    // Discord's limit on channel names
+    const NAME_LIMIT: usize = 100;
+
+    let title = msg.content.lines()
+        .map(|line| line.split_whitespace().filter(|word| !word.contains("://")).collect::<Vec<_>>().join(" "))
+        .find(|line| !line.is_empty());
+
+    let name = title.unwrap_or_else(|| format!("{}'s post", msg.author.display_name()));
+    name.chars().take(NAME_LIMIT).collect()
+}
+
+/// DynamicProcessor
+pub async fn restore_vote_reaction(ctx: &ReplyCtx, reaction: &Reaction) -> Result<()>
+{
+    if reaction.user_id != Some(ctx.cache().current_user().id) { return Ok(()); }
+
+    restore_vote_reactions(ctx, reaction.guild_id, reaction.channel_id, reaction.message_id, Some(&reaction.emoji)).await
+}
+
+/// DynamicProcessor
+pub async fn restore_cleared_vote_reactions(ctx: &ReplyCtx, removed: &ReactionRemoveAll) -> Result<()>
+{
+    restore_vote_reactions(ctx, removed.guild_id, removed.channel_id, removed.message_id, None).await
+}
+
+/// DynamicProcessor
+pub async fn restore_cleared_vote_emoji(ctx: &ReplyCtx, ReactionRemoveEmoji(removed): &ReactionRemoveEmoji) -> Result<()>
+{
+    restore_vote_reactions(ctx, removed.guild_id, removed.channel_id, removed.message_id, Some(&removed.emoji)).await
+}
+
+/// Re-adds Edward's vote reactions (or just `only`) to an indexed post that lost them.
+async fn restore_vote_reactions(
+    ctx: &ReplyCtx,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    only: Option<&ReactionType>
+) -> Result<()> {
+    let config = shared::get::<config::Config>(ctx.data()).await;
+    let Some(guild) = guild_id.and_then(|id| config.guild(id)) else { return Ok(()) };
+
+    // posts are indexed under their forum, not the thread the reaction is in
+    let Some(post) = shared::get::<store::Store>(ctx.data()).await.post(message_id)? else { return Ok(()) };
+    if !guild.is_watched_channel(post.channel_id) { return Ok(()); }
+
+    for reaction in vote_reactions(guild) {
+        if only.is_some_and(|only| emoji_key(only) != emoji_key(&reaction)) { continue; }
+
+        retry(3, reaction, async |reaction| ctx.react(channel_id, message_id, reaction).await).await
+            .with_context(|| format!("restoring vote reaction on {message_id}"))?;
     }
 
-    Propagation::Propagate
+    Ok(())
 }
 
+fn vote_reactions(guild: &GuildConfig) -> [ReactionType; 2]
+{
+    [
+        ReactionType::Custom { animated: false, id: guild.emojis.upvote, name: Some("upvote".to_string()), },
+        ReactionType::Custom { animated: false, id: guild.emojis.downvote, name: Some("downvote".to_string()) }
+    ]
+}
 
-async fn add_vote_reactions(ctx: &Context, msg: &Message)
+/// Only adds the ones Edward hasn't already placed, so edits don't re-react.
+async fn add_vote_reactions(ctx: &ReplyCtx, msg: &Message, guild: &GuildConfig) -> Result<()>
 {
-    let reactions = [
-        ReactionType::Custom { animated: false, id: EmojiId::new(1343553189508681728), name: Some("upvote".to_string()), },
-        ReactionType::Custom { animated: false, id: EmojiId::new(1343558658872709141), name: Some("downvote".to_string()) }
-    ];
+    for reaction in vote_reactions(guild) {
+        if has_reacted(msg, &reaction) { continue; }
 
-    for reaction in reactions {
-        retry(3, reaction, async |reaction| msg.react(&ctx.http, reaction).await).await.unwrap();
+        retry(3, reaction, async |reaction| ctx.react(msg.channel_id, msg.id, reaction).await).await
+            .with_context(|| format!("adding vote reaction to {}", msg.id))?;
     }
+
+    Ok(())
+}
+
+/// Takes Edward's votes back off a message that was edited into a non-post. It leaves the index
+/// first: `restore_vote_reaction` puts back Edward's reactions on anything still indexed.
+async fn remove_vote_reactions(ctx: &ReplyCtx, msg: &Message, guild: &GuildConfig) -> Result<()>
+{
+    shared::get::<store::Store>(ctx.data()).await.delete_post(msg.id)
+        .with_context(|| format!("removing post {} from the index", msg.id))?;
+
+    for reaction in vote_reactions(guild) {
+        if !has_reacted(msg, &reaction) { continue; }
+
+        ctx.unreact(msg.channel_id, msg.id, reaction).await
+            .with_context(|| format!("removing vote reaction from {}", msg.id))?;
+    }
+
+    Ok(())
+}
+
+fn has_reacted(msg: &Message, reaction: &ReactionType) -> bool
+{
+    msg.reactions.iter().any(|r| r.me && emoji_key(&r.reaction_type) == emoji_key(reaction))
 }
 
 async fn retry<T, U, E, Fut>(mut retry_number: usize, argument: T, f: impl Fn(T) -> Fut) -> Result<U>
@@ -94,7 +324,74 @@ async fn retry<T, U, E, Fut>(mut retry_number: usize, argument: T, f: impl Fn(T)
                 eprintln!("[retry #{retry_number}]: {why:?}");
                 retry_number -= 1;
             }
-            Err(_) => return Err(anyhow!("retry limit reached!"))
+            Err(why) => return Err(anyhow!("retry limit reached, last error: {why:?}"))
         }
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const SHOWCASE: ChannelId = ChannelId::new(100);
+    const VOTE: ChannelId = ChannelId::new(200);
+    const OTHER: ChannelId = ChannelId::new(300);
+    const THREAD: ChannelId = ChannelId::new(500);
+
+    fn guild() -> GuildConfig
+    {
+        toml::from_str("
+            id = 1
+            channels = { showcase = [100], vote = [200] }
+            emojis = { upvote = 10, downvote = 11 }
+        ").unwrap()
+    }
+
+    fn message(id: u64, channel_id: ChannelId) -> Message
+    {
+        let mut msg = Message::default();
+        msg.id = MessageId::new(id);
+        msg.channel_id = channel_id;
+        msg
+    }
+
+    #[test]
+    fn messages_straight_in_watched_channels_are_posts()
+    {
+        let guild = guild();
+
+        assert_eq!(placement(&guild, &message(1, SHOWCASE), None), Some(Placement::Post(SHOWCASE)));
+        assert_eq!(placement(&guild, &message(1, VOTE), None), Some(Placement::Post(VOTE)));
+        assert_eq!(placement(&guild, &message(1, OTHER), None), None);
+    }
+
+    #[test]
+    fn forum_starters_are_posts_in_their_forum_and_the_rest_of_the_thread_replies()
+    {
+        let guild = guild();
+        let (starter, reply) = (message(THREAD.get(), THREAD), message(501, THREAD));
+
+        assert!(is_forum_starter(&starter));
+        assert!(!is_forum_starter(&reply));
+
+        assert_eq!(placement(&guild, &starter, Some(SHOWCASE)), Some(Placement::Post(SHOWCASE)));
+        assert_eq!(placement(&guild, &reply, Some(SHOWCASE)), Some(Placement::Reply(SHOWCASE)));
+        assert_eq!(placement(&guild, &reply, Some(VOTE)), Some(Placement::Reply(VOTE)));
+
+        // threads elsewhere are none of Edward's business
+        assert_eq!(placement(&guild, &starter, Some(OTHER)), None);
+        assert_eq!(placement(&guild, &reply, Some(OTHER)), None);
+    }
+
+    #[test]
+    fn messages_that_threads_were_opened_on_stay_in_their_channel()
+    {
+        // a text channel thread shares its id with the message it was opened on,
+        // but that message is in the channel, not the thread
+        let opened_on = message(THREAD.get(), SHOWCASE);
+
+        assert!(!is_forum_starter(&opened_on));
+        assert_eq!(placement(&guild(), &opened_on, None), Some(Placement::Post(SHOWCASE)));
+    }
+}
@@ -0,0 +1,54 @@
+use std::{collections::{HashMap, HashSet}, sync::{Arc, PoisonError}};
+
+use serenity::model::id::GuildId;
+use poise::serenity_prelude as serenity;
+use anyhow::Result;
+
+use crate::store::Store;
+
+/// Names of the systems switched off in one guild.
+pub type Disabled = HashSet<String>;
+
+/// Per-guild system switches, cached in memory and written through to the store
+/// so they survive restarts.
+pub struct SystemToggles
+{
+    disabled: std::sync::RwLock<HashMap<GuildId, Arc<Disabled>>>,
+    store: Arc<Store>,
+}
+
+impl SystemToggles
+{
+    pub fn load(store: Arc<Store>) -> Result<Self>
+    {
+        let mut disabled: HashMap<GuildId, Disabled> = HashMap::new();
+        for (guild_id, system) in store.disabled_systems()? {
+            disabled.entry(guild_id).or_default().insert(system);
+        }
+
+        Ok(SystemToggles {
+            disabled: std::sync::RwLock::new(disabled.into_iter().map(|(id, systems)| (id, Arc::new(systems))).collect()),
+            store,
+        })
+    }
+
+    /// What's switched off for the event's guild. Events outside guilds run everything.
+    pub fn disabled(&self, guild_id: Option<GuildId>) -> Arc<Disabled>
+    {
+        guild_id
+            .and_then(|id| self.disabled.read().unwrap_or_else(PoisonError::into_inner).get(&id).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the system's state actually changed.
+    pub fn set_enabled(&self, guild_id: GuildId, system: &str, enabled: bool) -> Result<bool>
+    {
+        let mut disabled = self.disabled.write().unwrap_or_else(PoisonError::into_inner);
+        if !self.store.set_system_enabled(guild_id, system, enabled)? { return Ok(false); }
+
+        let systems = Arc::make_mut(disabled.entry(guild_id).or_default());
+        if enabled { systems.remove(system); } else { systems.insert(system.to_owned()); }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,141 @@
+use serenity::model::{id::MessageId, Timestamp};
+use poise::serenity_prelude as serenity;
+
+const DAY: i64 = 24 * 60 * 60;
+/// Ids of anything made since 2015 have at least this many digits.
+const MIN_SNOWFLAKE_DIGITS: usize = 17;
+
+/// The `period` presets offered by `/fetch`, counted back from now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Period
+{
+    #[name = "week"]
+    Week,
+    #[name = "month"]
+    Month,
+    #[name = "year"]
+    Year,
+    #[name = "all"]
+    All,
+}
+
+impl Period
+{
+    fn days(self) -> Option<i64>
+    {
+        match self {
+            Period::Week => Some(7),
+            Period::Month => Some(30),
+            Period::Year => Some(365),
+            Period::All => None,
+        }
+    }
+}
+
+/// The stretch of a channel's history `/fetch` looks at: posts from `since` on and before `until`.
+/// Either end left open reaches the start of the channel or now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Window
+{
+    pub since: Option<Timestamp>,
+    pub until: Option<Timestamp>,
+}
+
+impl Window
+{
+    /// Builds the window from `/fetch`'s options, which take either a `period` or `since`.
+    /// Bounds are dates (`2025-01-31`), RFC 3339 timestamps or message ids/links;
+    /// a date `until` includes that whole day.
+    pub fn new(period: Option<Period>, since: Option<&str>, until: Option<&str>, now: Timestamp) -> Result<Window, String>
+    {
+        let since = match (period, since) {
+            (Some(_), Some(_)) => return Err("You can only specify either `period` or `since`, not both!".to_owned()),
+            (Some(period), None) => period.days().map(|days| at(now.unix_timestamp() - days * DAY)),
+            (None, Some(since)) => Some(parse_bound(since, false)?),
+            (None, None) => None,
+        };
+        let until = until.map(|until| parse_bound(until, true)).transpose()?;
+
+        if let (Some(since), Some(until)) = (since, until) {
+            if since >= until { return Err("`since` has to come before `until`.".to_owned()); }
+        }
+
+        Ok(Window { since, until })
+    }
+}
+
+fn at(unix_timestamp: i64) -> Timestamp
+{
+    Timestamp::from_unix_timestamp(unix_timestamp).expect("window bounds stay within chrono's range")
+}
+
+/// `end_of_day` moves plain dates to the following midnight, so they work as exclusive upper bounds.
+fn parse_bound(bound: &str, end_of_day: bool) -> Result<Timestamp, String>
+{
+    let bound = bound.trim();
+
+    // message links end in the message id; shorter numbers are years or unix timestamps, not ids
+    let snowflake = bound.rsplit('/').next().unwrap_or(bound);
+    if snowflake.len() >= MIN_SNOWFLAKE_DIGITS && snowflake.bytes().all(|b| b.is_ascii_digit()) {
+        if let Some(id) = snowflake.parse::<u64>().ok().filter(|&id| id != 0) {
+            return Ok(MessageId::new(id).created_at());
+        }
+    }
+
+    if let Ok(timestamp) = Timestamp::parse(bound) { return Ok(timestamp); }
+
+    match Timestamp::parse(&format!("{bound}T00:00:00Z")) {
+        Ok(midnight) if end_of_day => Ok(at(midnight.unix_timestamp() + DAY)),
+        Ok(midnight) => Ok(midnight),
+        Err(_) => Err(format!("`{bound}` is not a date like `2025-01-31`, a timestamp or a message id.")),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn now() -> Timestamp
+    {
+        Timestamp::parse("2025-06-15T12:00:00Z").unwrap()
+    }
+
+    #[test]
+    fn periods_count_back_from_now()
+    {
+        let week = Window::new(Some(Period::Week), None, None, now()).unwrap();
+        assert_eq!(week.since, Some(Timestamp::parse("2025-06-08T12:00:00Z").unwrap()));
+        assert_eq!(week.until, None);
+
+        assert_eq!(Window::new(Some(Period::All), None, None, now()).unwrap(), Window::default());
+    }
+
+    #[test]
+    fn bounds_take_dates_timestamps_and_message_ids()
+    {
+        let window = Window::new(None, Some("2025-01-01"), Some("2025-01-31"), now()).unwrap();
+        assert_eq!(window.since, Some(Timestamp::parse("2025-01-01T00:00:00Z").unwrap()));
+        assert_eq!(window.until, Some(Timestamp::parse("2025-02-01T00:00:00Z").unwrap()));
+
+        let window = Window::new(None, Some("2025-01-01T08:30:00Z"), None, now()).unwrap();
+        assert_eq!(window.since, Some(Timestamp::parse("2025-01-01T08:30:00Z").unwrap()));
+
+        let id = MessageId::new(1343553189508681728);
+        let link = format!("https://discord.com/channels/647981638348832790/677869233803100171/{id}");
+        assert_eq!(Window::new(None, Some(&link), None, now()).unwrap().since, Some(id.created_at()));
+        assert_eq!(Window::new(None, None, Some(&id.to_string()), now()).unwrap().until, Some(id.created_at()));
+    }
+
+    #[test]
+    fn bad_windows_are_rejected()
+    {
+        assert!(Window::new(Some(Period::Week), Some("2025-01-01"), None, now()).is_err());
+        assert!(Window::new(None, Some("last tuesday"), None, now()).is_err());
+        assert!(Window::new(None, Some("2025-02-01"), Some("2025-01-01"), now()).is_err());
+
+        // bare numbers too short to be message ids
+        assert!(Window::new(None, Some("2025"), None, now()).is_err());
+        assert!(Window::new(None, Some("1735689600"), None, now()).is_err());
+    }
+}
@@ -17,7 +17,7 @@ if [ -n "${1:-}" ]; then
   set -e
 
   echo 'resuming server instance..'
-  ssh "$REMOTE_SERVER" 'tmux send-keys "./dev" Enter'
+  ssh "$REMOTE_SERVER" 'tmux send-keys "./dev ~/edward.toml" Enter'
 else
   echo "no remote server address given"
   echo